@@ -0,0 +1,234 @@
+use super::violation::{Violation, ViolationReport};
+use anyhow::{Context, Error};
+use cargo_metadata::DependencyKind;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Violations accepted when the tool was adopted. Violations listed here are
+/// reported as known and do not fail the check.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub violations: Vec<BaselineEntry>,
+}
+
+/// A violation is identified by the two packages it connects and its kind,
+/// so that it stays known when only its details, such as the resolved
+/// version, change. The rules file of the violated rule is left out, as its
+/// path depends on the directory the check runs from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub parent: String,
+    pub dependency: String,
+    /// The kind of the violation, followed by the dependency kind of its
+    /// first edge unless that is a normal dependency.
+    pub kind: String,
+    /// The violation as printed when the baseline was written.
+    pub message: String,
+}
+
+impl BaselineEntry {
+    fn new(violation: &Violation) -> Self {
+        Self {
+            parent: violation.parent.clone(),
+            dependency: violation.dependency.clone(),
+            kind: kind_of(violation),
+            message: violation.to_string(),
+        }
+    }
+
+    fn matches(&self, violation: &Violation) -> bool {
+        self.parent == violation.parent
+            && self.dependency == violation.dependency
+            && self.kind == kind_of(violation)
+    }
+}
+
+fn kind_of(violation: &Violation) -> String {
+    match violation.dependency_kind {
+        DependencyKind::Normal => violation.kind.id(),
+        kind => format!("{} ({kind})", violation.kind.id()),
+    }
+}
+
+impl Baseline {
+    pub fn from_report(report: &ViolationReport) -> Self {
+        let mut violations: Vec<BaselineEntry> =
+            report.violations.iter().map(BaselineEntry::new).collect();
+        violations.sort();
+
+        Self { violations }
+    }
+
+    #[tracing::instrument(skip_all, fields(path = ?path.as_ref()))]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read baseline from '{}'", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse baseline in '{}'", path.display()))
+    }
+
+    #[tracing::instrument(skip_all, fields(path = ?path.as_ref()))]
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text + "\n")
+            .with_context(|| format!("failed to write baseline to '{}'", path.display()))
+    }
+
+    /// Marks the violations listed in the baseline as known and returns the
+    /// entries that no longer occur.
+    pub fn apply(&self, report: &mut ViolationReport) -> Vec<&BaselineEntry> {
+        for violation in &mut report.violations {
+            violation.known = self.violations.iter().any(|entry| entry.matches(violation));
+        }
+
+        self.violations
+            .iter()
+            .filter(|entry| {
+                !report
+                    .violations
+                    .iter()
+                    .any(|violation| entry.matches(violation))
+            })
+            .collect()
+    }
+}
+
+pub fn print_fixed(writer: &mut impl Write, fixed: &[&BaselineEntry]) -> Result<(), Error> {
+    if fixed.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "{} baseline violation(s) no longer occur and can be removed from the baseline:",
+        fixed.len()
+    )?;
+    for entry in fixed {
+        writeln!(writer, "  {}", entry.message)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::tests::demo_graph;
+    use crate::dependency_graph::violation::check_violations;
+    use crate::dependency_rule::DependencyRules;
+    use anyhow::Result;
+
+    fn tangled_report(rules_path: &str) -> Result<ViolationReport> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file(rules_path)?;
+        Ok(check_violations(&graph, &rules))
+    }
+
+    #[test]
+    fn test_baseline_round_trip() -> Result<()> {
+        let report = tangled_report("tests/demo_crates/tangled-clean-arch/dependency_rules.toml")?;
+        let baseline = Baseline::from_report(&report);
+        assert_eq!(baseline.violations.len(), report.violations.len());
+        assert!(baseline.violations.is_sorted());
+
+        let dir = std::env::temp_dir().join("check_deprule_test_baseline");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("baseline.json");
+        baseline.write(&path)?;
+        assert_eq!(Baseline::read(&path)?, baseline);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline_marks_known_and_fixed_violations() -> Result<()> {
+        let baseline = Baseline::read("tests/test_files/baseline_test.json")?;
+        let mut report =
+            tangled_report("tests/demo_crates/tangled-clean-arch/dependency_rules.toml")?;
+
+        let fixed = baseline.apply(&mut report);
+
+        let new: Vec<_> = report
+            .violations
+            .iter()
+            .filter(|violation| !violation.known)
+            .map(|violation| (violation.parent.as_str(), violation.dependency.as_str()))
+            .collect();
+        assert_eq!(new, vec![("tangled-ca-interactor", "tangled-ca-repositry")]);
+        assert_eq!(fixed.len(), 1);
+        assert_eq!(fixed[0].parent, "tangled-ca-handler");
+
+        let mut buf = Vec::new();
+        print_fixed(&mut buf, &fixed)?;
+        let output = String::from_utf8(buf)?;
+        assert!(output.contains("1 baseline violation(s) no longer occur"));
+        assert!(output.contains("  tangled-ca-handler -> tangled-ca-core: forbidden dependency"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline_applies_from_another_directory() -> Result<()> {
+        let rules_path = "tests/demo_crates/tangled-clean-arch/dependency_rules.toml";
+        let dir = std::env::temp_dir().join("check_deprule_test_baseline_directory");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("baseline.json");
+        Baseline::from_report(&tangled_report(rules_path)?).write(&path)?;
+
+        // the same rules file, as seen from outside of the workspace
+        let absolute = std::env::current_dir()?.join(rules_path);
+        let mut report = tangled_report(absolute.to_str().unwrap())?;
+        let baseline = Baseline::read(&path)?;
+        let fixed = baseline.apply(&mut report);
+
+        assert!(fixed.is_empty());
+        assert!(report.violations.iter().all(|violation| violation.known));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline_reports_other_kinds_on_known_pairs() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/budget_rules_test.toml")?;
+        let mut report = check_violations(&graph, &rules);
+
+        // only the direct budget of ca-handler was accepted
+        let baseline = Baseline {
+            violations: Baseline::from_report(&report)
+                .violations
+                .into_iter()
+                .filter(|entry| entry.kind == "direct-budget" && entry.parent == "ca-handler")
+                .collect(),
+        };
+        let fixed = baseline.apply(&mut report);
+
+        assert!(fixed.is_empty());
+        let new: Vec<String> = report
+            .violations
+            .iter()
+            .filter(|violation| !violation.known)
+            .map(|violation| format!("{} {}", violation.parent, violation.kind.id()))
+            .collect();
+        assert_eq!(
+            new,
+            vec![
+                "ca-handler transitive-budget",
+                "ca-repositry transitive-budget"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_invalid_baseline() {
+        let result = Baseline::read("tests/test_files/parse_rules_test.toml");
+        assert!(format!("{:#}", result.unwrap_err()).contains("failed to parse baseline in"));
+    }
+}
@@ -0,0 +1,100 @@
+use super::Graph;
+use cargo_metadata::DependencyKind;
+use petgraph::EdgeDirection;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+/// Number of contributors listed with a budget violation.
+const CONTRIBUTOR_COUNT: usize = 3;
+
+/// How many crates a package depends on, following edges of some kinds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DependencyUsage {
+    pub(crate) direct: usize,
+    pub(crate) transitive: usize,
+    /// Direct dependencies that pull in the most crates, each with the number
+    /// of crates it brings in, itself included.
+    pub(crate) contributors: Vec<(String, usize)>,
+}
+
+pub(crate) fn dependency_usage(
+    graph: &Graph,
+    start: NodeIndex,
+    kinds: &[DependencyKind],
+) -> DependencyUsage {
+    let direct: BTreeSet<NodeIndex> = dependencies(graph, start, kinds).collect();
+    let transitive = reachable(graph, start, &[start], kinds).len();
+
+    let mut contributors: Vec<(String, usize)> = direct
+        .iter()
+        .map(|&dependency| {
+            let count = reachable(graph, dependency, &[start, dependency], kinds).len() + 1;
+            (graph.graph[dependency].name.to_string(), count)
+        })
+        .collect();
+    contributors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    contributors.truncate(CONTRIBUTOR_COUNT);
+
+    DependencyUsage {
+        direct: direct.len(),
+        transitive,
+        contributors,
+    }
+}
+
+fn dependencies(
+    graph: &Graph,
+    node: NodeIndex,
+    kinds: &[DependencyKind],
+) -> impl Iterator<Item = NodeIndex> {
+    graph
+        .graph
+        .edges_directed(node, EdgeDirection::Outgoing)
+        .filter(|edge| kinds.contains(&edge.weight().kind))
+        .map(|edge| edge.target())
+}
+
+/// Packages reachable from `start`, leaving out the `excluded` ones.
+pub(super) fn reachable(
+    graph: &Graph,
+    start: NodeIndex,
+    excluded: &[NodeIndex],
+    kinds: &[DependencyKind],
+) -> HashSet<NodeIndex> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        for next in dependencies(graph, current, kinds) {
+            if !excluded.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::tests::{demo_graph, node};
+    use anyhow::Result;
+
+    #[test]
+    fn test_dependency_usage() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
+        let kinds = [DependencyKind::Normal, DependencyKind::Build];
+
+        let usage = dependency_usage(&graph, node(&graph, "ca-repositry"), &kinds);
+        assert_eq!(usage.direct, 1);
+        assert_eq!(usage.transitive, 2);
+        assert_eq!(usage.contributors, vec![("ca-interactor".to_string(), 2)]);
+
+        let usage = dependency_usage(&graph, node(&graph, "ca-handler"), &kinds);
+        assert_eq!(usage.direct, 3);
+        assert!(usage.transitive > 10);
+        assert_eq!(usage.contributors[0].0, "actix-web");
+        assert_eq!(usage.contributors.len(), 3);
+        Ok(())
+    }
+}
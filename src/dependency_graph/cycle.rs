@@ -0,0 +1,187 @@
+use super::{Edge, Graph};
+use cargo_metadata::DependencyKind;
+use petgraph::EdgeDirection;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::stable_graph::EdgeReference;
+use petgraph::visit::{EdgeFiltered, EdgeRef};
+use semver::Version;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A dependency cycle, starting and ending at the same package.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cycle {
+    pub(crate) nodes: Vec<NodeIndex>,
+    /// Kind of each edge, so `kinds[i]` connects `nodes[i]` and `nodes[i + 1]`.
+    pub(crate) kinds: Vec<DependencyKind>,
+}
+
+/// Finds the cycles inside strongly connected components, following only
+/// edges of the given kinds. Every edge inside a component lies on a cycle,
+/// so each of them is reported with the shortest cycle starting at it,
+/// unless that cycle was already reported starting at another of its edges.
+/// Edges are visited in the order of their package names.
+pub(crate) fn find_cycles(graph: &Graph, kinds: &[DependencyKind]) -> Vec<Cycle> {
+    let filtered = EdgeFiltered::from_fn(&graph.graph, |edge| kinds.contains(&edge.weight().kind));
+
+    let mut cycles: Vec<Cycle> = Vec::new();
+    for component in tarjan_scc(&filtered) {
+        if component.len() < 2 {
+            continue;
+        }
+        let members: HashSet<NodeIndex> = component.into_iter().collect();
+        let mut edges: Vec<_> = members
+            .iter()
+            .flat_map(|&member| graph.graph.edges_directed(member, EdgeDirection::Outgoing))
+            .filter(|edge| kinds.contains(&edge.weight().kind) && members.contains(&edge.target()))
+            .collect();
+        edges.sort_by_key(|edge| edge_key(graph, edge.source(), edge.target(), edge.weight().kind));
+
+        for edge in edges {
+            let Some(cycle) = shortest_cycle(graph, kinds, &members, edge) else {
+                continue;
+            };
+            if !cycles.iter().any(|known| known.is_rotation_of(&cycle)) {
+                cycles.push(cycle);
+            }
+        }
+    }
+    cycles.sort_by_key(|cycle| edge_key(graph, cycle.nodes[0], cycle.nodes[1], cycle.kinds[0]));
+    cycles
+}
+
+fn edge_key(
+    graph: &Graph,
+    source: NodeIndex,
+    target: NodeIndex,
+    kind: DependencyKind,
+) -> (&str, &Version, &str, &Version, String) {
+    let (source, target) = (&graph.graph[source], &graph.graph[target]);
+    (
+        &source.name,
+        &source.version,
+        &target.name,
+        &target.version,
+        kind.to_string(),
+    )
+}
+
+impl Cycle {
+    /// Whether both cycles pass the same edges, starting at different ones.
+    fn is_rotation_of(&self, other: &Cycle) -> bool {
+        let edges = self.kinds.len();
+        if edges != other.kinds.len() {
+            return false;
+        }
+        (0..edges).any(|offset| {
+            (0..edges).all(|i| {
+                let j = (i + offset) % edges;
+                self.nodes[i] == other.nodes[j] && self.kinds[i] == other.kinds[j]
+            })
+        })
+    }
+}
+
+/// The edge followed by the shortest way back to its source inside one
+/// component, found with a breadth-first search.
+fn shortest_cycle(
+    graph: &Graph,
+    kinds: &[DependencyKind],
+    members: &HashSet<NodeIndex>,
+    edge: EdgeReference<'_, Edge>,
+) -> Option<Cycle> {
+    let (start, first) = (edge.source(), edge.target());
+    let mut predecessors: HashMap<NodeIndex, (NodeIndex, DependencyKind)> = HashMap::new();
+    let mut queue = VecDeque::from([first]);
+
+    while let Some(current) = queue.pop_front() {
+        if current == start {
+            break;
+        }
+        for next_edge in graph.graph.edges_directed(current, EdgeDirection::Outgoing) {
+            let next = next_edge.target();
+            if !kinds.contains(&next_edge.weight().kind)
+                || !members.contains(&next)
+                || next == first
+                || predecessors.contains_key(&next)
+            {
+                continue;
+            }
+            predecessors.insert(next, (current, next_edge.weight().kind));
+            queue.push_back(next);
+        }
+    }
+    if first != start && !predecessors.contains_key(&start) {
+        return None;
+    }
+
+    let mut nodes = vec![start];
+    let mut edge_kinds = Vec::new();
+    let mut node = start;
+    while let Some(&(previous, kind)) = predecessors.get(&node) {
+        nodes.push(previous);
+        edge_kinds.push(kind);
+        node = previous;
+    }
+    nodes.push(start);
+    edge_kinds.push(edge.weight().kind);
+    nodes.reverse();
+    edge_kinds.reverse();
+    Some(Cycle {
+        nodes,
+        kinds: edge_kinds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::tests::demo_graph;
+    use anyhow::Result;
+
+    const ALL_KINDS: [DependencyKind; 3] = [
+        DependencyKind::Normal,
+        DependencyKind::Build,
+        DependencyKind::Development,
+    ];
+
+    fn names(graph: &Graph, cycle: &Cycle) -> Vec<String> {
+        cycle
+            .nodes
+            .iter()
+            .map(|node| graph.graph[*node].name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_dev_dependency_cycles() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/cycles/Cargo.toml")?;
+
+        let cycles = find_cycles(&graph, &ALL_KINDS);
+
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(
+            names(&graph, &cycles[0]),
+            vec!["cycles-core", "cycles-mocks", "cycles-core"]
+        );
+        assert_eq!(
+            names(&graph, &cycles[1]),
+            vec!["cycles-core", "cycles-test-utils", "cycles-core"]
+        );
+        assert_eq!(
+            cycles[1].kinds,
+            vec![DependencyKind::Development, DependencyKind::Normal]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cycles_ignores_other_kinds() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/cycles/Cargo.toml")?;
+
+        let cycles = find_cycles(&graph, &[DependencyKind::Normal, DependencyKind::Build]);
+
+        assert!(cycles.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use super::Graph;
+use super::budget::reachable;
+use cargo_metadata::DependencyKind;
+use petgraph::graph::NodeIndex;
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A crate resolved to several semver-incompatible versions.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DuplicateVersions {
+    pub(crate) name: String,
+    /// Each resolved version with the workspace members that pull it in.
+    pub(crate) versions: Vec<(Version, Vec<String>)>,
+    /// Number of semver-incompatible version ranges among `versions`.
+    pub(crate) incompatible: usize,
+}
+
+/// Finds crates that appear in the graph with more than one
+/// semver-incompatible version, ordered by crate name.
+pub(crate) fn find_duplicate_versions(graph: &Graph) -> Vec<DuplicateVersions> {
+    let mut by_name: BTreeMap<&str, Vec<NodeIndex>> = BTreeMap::new();
+    for &node in graph.nodes.values() {
+        by_name
+            .entry(graph.graph[node].name.as_str())
+            .or_default()
+            .push(node);
+    }
+
+    let mut members: Vec<NodeIndex> = graph
+        .workspace_members
+        .iter()
+        .filter_map(|id| graph.nodes.get(id).copied())
+        .collect();
+    members.sort_by(|a, b| graph.graph[*a].name.cmp(&graph.graph[*b].name));
+    let kinds = [
+        DependencyKind::Normal,
+        DependencyKind::Build,
+        DependencyKind::Development,
+    ];
+    let dependencies: Vec<_> = members
+        .iter()
+        .map(|&member| reachable(graph, member, &[member], &kinds))
+        .collect();
+
+    by_name
+        .into_iter()
+        .filter_map(|(name, mut nodes)| {
+            let incompatible = nodes
+                .iter()
+                .map(|node| compatibility(&graph.graph[*node].version))
+                .collect::<BTreeSet<_>>()
+                .len();
+            if incompatible < 2 {
+                return None;
+            }
+
+            nodes.sort_by(|a, b| graph.graph[*a].version.cmp(&graph.graph[*b].version));
+            let versions = nodes
+                .iter()
+                .map(|node| {
+                    let pulled_by = members
+                        .iter()
+                        .zip(&dependencies)
+                        .filter(|(_, reachable)| reachable.contains(node))
+                        .map(|(member, _)| graph.graph[*member].name.to_string())
+                        .collect();
+                    (graph.graph[*node].version.clone(), pulled_by)
+                })
+                .collect();
+            Some(DuplicateVersions {
+                name: name.to_string(),
+                versions,
+                incompatible,
+            })
+        })
+        .collect()
+}
+
+/// The leftmost non-zero part of a version, which cargo treats as the
+/// boundary of semver compatibility.
+fn compatibility(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::tests::demo_graph;
+    use anyhow::Result;
+
+    #[test]
+    fn test_find_duplicate_versions() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/duplicates/Cargo.toml")?;
+
+        let duplicates = find_duplicate_versions(&graph);
+
+        let names: Vec<&str> = duplicates.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["bitflags", "itoa"]);
+        let itoa = &duplicates[1];
+        assert_eq!(itoa.incompatible, 2);
+        assert_eq!(itoa.versions[0].0.major, 0);
+        assert_eq!(itoa.versions[0].1, vec!["dup-app"]);
+        assert_eq!(itoa.versions[1].0.major, 1);
+        assert_eq!(itoa.versions[1].1, vec!["dup-app", "dup-lib"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let compatible = |a: &str, b: &str| {
+            compatibility(&Version::parse(a).unwrap()) == compatibility(&Version::parse(b).unwrap())
+        };
+        assert!(compatible("1.0.0", "1.9.3"));
+        assert!(!compatible("1.0.0", "2.0.0"));
+        assert!(compatible("0.4.1", "0.4.8"));
+        assert!(!compatible("0.4.8", "0.5.0"));
+        assert!(!compatible("0.0.1", "0.0.2"));
+    }
+}
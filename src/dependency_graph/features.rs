@@ -0,0 +1,243 @@
+use super::{Edge, Graph};
+use cargo_metadata::{Dependency, Package};
+use petgraph::EdgeDirection;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Features enabled on each package reachable from a workspace member.
+pub(crate) type MemberFeatures = HashMap<NodeIndex, BTreeSet<String>>;
+
+impl Graph {
+    /// Features the resolver enabled on the package.
+    pub(crate) fn enabled_features(&self, node: NodeIndex) -> &[String] {
+        self.features
+            .get(&self.graph[node].id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// Computes the features enabled when only `start` is built with its default
+/// features, following the edges accepted by `follow`. The resolver unifies
+/// features across the whole workspace, so its features are only used to
+/// leave out features it did not enable at all.
+pub(crate) fn member_features(
+    graph: &Graph,
+    start: NodeIndex,
+    follow: impl Fn(&Edge) -> bool,
+) -> MemberFeatures {
+    let mut features = MemberFeatures::new();
+    features.insert(start, enable(graph, start, ["default".to_string()]));
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(current) = queue.pop_front() {
+        for edge in graph.graph.edges_directed(current, EdgeDirection::Outgoing) {
+            let next = edge.target();
+            if !follow(edge.weight()) || next == start {
+                continue;
+            }
+            let current_features = &features[&current];
+            if active_declarations(graph, current_features, current, next)
+                .next()
+                .is_none()
+            {
+                continue;
+            }
+            let requested = requested_features(graph, current_features, current, next);
+            let enabled = enable(graph, next, requested);
+            let known = features.get(&next);
+            if known.is_some_and(|known| enabled.is_subset(known)) {
+                continue;
+            }
+            features.entry(next).or_default().extend(enabled);
+            queue.push_back(next);
+        }
+    }
+    features
+}
+
+/// Returns the feature of `package` that `dependent` requests and that turns
+/// `feature` on, either listed in its dependency declaration, through default
+/// features, or through a `package/feature` entry of one of its own enabled
+/// features. Returns `None` when `dependent` does not turn the feature on.
+pub(crate) fn requested_feature(
+    graph: &Graph,
+    features: &MemberFeatures,
+    dependent: NodeIndex,
+    package: NodeIndex,
+    feature: &str,
+) -> Option<String> {
+    let dependent_features = features.get(&dependent)?;
+    let requested = requested_features(graph, dependent_features, dependent, package);
+
+    if requested.iter().any(|requested| requested == feature) {
+        return Some(feature.to_string());
+    }
+    let package = &graph.graph[package];
+    requested
+        .into_iter()
+        .find(|requested| implies(package, requested, feature, &mut HashSet::new()))
+}
+
+/// Features of `package` that `dependent` asks for while `dependent_features`
+/// are enabled on it.
+fn requested_features(
+    graph: &Graph,
+    dependent_features: &BTreeSet<String>,
+    dependent: NodeIndex,
+    package: NodeIndex,
+) -> Vec<String> {
+    let mut requested: Vec<String> = Vec::new();
+    for dependency in active_declarations(graph, dependent_features, dependent, package) {
+        requested.extend(dependency.features.iter().cloned());
+        if dependency.uses_default_features {
+            requested.push("default".to_string());
+        }
+
+        let dependent = &graph.graph[dependent];
+        let key = dependency.rename.as_deref().unwrap_or(&dependency.name);
+        for enabled in dependent_features {
+            let Some(items) = dependent.features.get(enabled) else {
+                continue;
+            };
+            requested.extend(items.iter().filter_map(|item| {
+                let (dependency, feature) = item.split_once('/')?;
+                (dependency.trim_end_matches('?') == key).then(|| feature.to_string())
+            }));
+        }
+    }
+    requested
+}
+
+/// Declarations of `package` in `dependent`, leaving out optional ones that
+/// none of `dependent_features` activates.
+fn active_declarations<'a>(
+    graph: &'a Graph,
+    dependent_features: &'a BTreeSet<String>,
+    dependent: NodeIndex,
+    package: NodeIndex,
+) -> impl Iterator<Item = &'a Dependency> {
+    let dependent = &graph.graph[dependent];
+    let package = &graph.graph[package];
+    dependent.dependencies.iter().filter(move |dependency| {
+        dependency.name == package.name.as_str()
+            && dependency.req.matches(&package.version)
+            && (!dependency.optional || activates(dependent, dependent_features, dependency))
+    })
+}
+
+/// Whether one of the enabled features turns the optional dependency on,
+/// as its implicit feature, with `dep:name` or with `name/feature`.
+fn activates(
+    dependent: &Package,
+    dependent_features: &BTreeSet<String>,
+    dependency: &Dependency,
+) -> bool {
+    let key = dependency.rename.as_deref().unwrap_or(&dependency.name);
+    dependent_features.iter().any(|enabled| {
+        enabled == key
+            || dependent.features.get(enabled).is_some_and(|items| {
+                items.iter().any(|item| {
+                    item.strip_prefix("dep:") == Some(key)
+                        || item.split_once('/').map(|(name, _)| name) == Some(key)
+                })
+            })
+    })
+}
+
+/// The requested features of the package together with the features they
+/// imply, limited to those the resolver enabled.
+fn enable(
+    graph: &Graph,
+    node: NodeIndex,
+    requested: impl IntoIterator<Item = String>,
+) -> BTreeSet<String> {
+    let package = &graph.graph[node];
+    let resolved = graph.enabled_features(node);
+    let mut enabled = BTreeSet::new();
+    let mut pending: Vec<String> = requested.into_iter().collect();
+    while let Some(feature) = pending.pop() {
+        if !resolved.contains(&feature) || !enabled.insert(feature.clone()) {
+            continue;
+        }
+        if let Some(items) = package.features.get(&feature) {
+            pending.extend(
+                items
+                    .iter()
+                    .filter(|item| !item.starts_with("dep:") && !item.contains('/'))
+                    .cloned(),
+            );
+        }
+    }
+    enabled
+}
+
+/// Whether enabling `from` on the package also enables `feature`.
+fn implies<'a>(
+    package: &'a Package,
+    from: &'a str,
+    feature: &str,
+    visited: &mut HashSet<&'a str>,
+) -> bool {
+    if from == feature {
+        return true;
+    }
+    if !visited.insert(from) {
+        return false;
+    }
+    package.features.get(from).is_some_and(|items| {
+        items
+            .iter()
+            .filter(|item| !item.starts_with("dep:") && !item.contains('/'))
+            .any(|item| implies(package, item, feature, visited))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::tests::{demo_graph, node};
+    use anyhow::Result;
+
+    #[test]
+    fn test_member_and_requested_features() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/features/Cargo.toml")?;
+
+        let lib = node(&graph, "feat-lib");
+        assert!(graph.enabled_features(lib).contains(&"std".to_string()));
+
+        let embedded = member_features(&graph, node(&graph, "feat-embedded"), |_| true);
+        assert!(embedded[&lib].is_empty());
+        assert_eq!(
+            requested_feature(&graph, &embedded, node(&graph, "feat-embedded"), lib, "std"),
+            None
+        );
+
+        let core = member_features(&graph, node(&graph, "feat-core"), |_| true);
+        assert!(core[&lib].contains("std"));
+        assert_eq!(
+            requested_feature(&graph, &core, node(&graph, "feat-util"), lib, "std"),
+            Some("full".to_string())
+        );
+        assert_eq!(
+            requested_feature(&graph, &core, node(&graph, "feat-core"), lib, "std"),
+            None
+        );
+        assert_eq!(
+            requested_feature(&graph, &core, node(&graph, "feat-util"), lib, "extra"),
+            Some("full".to_string())
+        );
+
+        let opt = member_features(&graph, node(&graph, "feat-opt"), |_| true);
+        assert!(!opt.contains_key(&lib));
+
+        let app = member_features(&graph, node(&graph, "feat-app"), |_| true);
+        assert!(app[&node(&graph, "feat-opt")].contains("withlib"));
+        assert_eq!(
+            requested_feature(&graph, &app, node(&graph, "feat-app"), lib, "std"),
+            Some("default".to_string())
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::dependency_rule::Severity;
 use anyhow::{Result, anyhow};
 use cargo_metadata::Package;
 use parse::{Parser, RawChunk};
@@ -8,7 +9,7 @@ mod parse;
 pub(crate) enum Chunk {
     Raw(String),
     Package,
-    ViolationPackage,
+    ViolationPackage(Severity),
     License,
     Repository,
 }
@@ -75,9 +76,14 @@ impl fmt::Display for Display<'_> {
                     write!(fmt, "{} v{}", self.package.name, self.package.version)?;
                     write_package_source(fmt, self.package)?;
                 }
-                Chunk::ViolationPackage => {
+                Chunk::ViolationPackage(severity) => {
                     let msg = format!("{} v{}", self.package.name, self.package.version);
-                    write!(fmt, "{}", msg.red())?;
+                    let msg = match severity {
+                        Severity::Error => msg.red(),
+                        Severity::Warning => msg.yellow(),
+                        Severity::Info => msg.blue(),
+                    };
+                    write!(fmt, "{msg}")?;
                     write_package_source(fmt, self.package)?;
                 }
                 Chunk::License => {
@@ -108,9 +114,7 @@ mod tests {
     #[test]
     fn test_chunk_violation_format() {
         let violation_package_display = Display {
-            pattern: &Pattern {
-                0: vec![Chunk::ViolationPackage],
-            },
+            pattern: &Pattern(vec![Chunk::ViolationPackage(Severity::Error)]),
             package: &cargo_metadata::PackageBuilder::new(
                 "package".to_string(),
                 Version::parse("1.0.0").unwrap(),
@@ -127,4 +131,26 @@ mod tests {
         let actual = format!("{}", violation_package_display);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_chunk_violation_colored_by_severity() {
+        let package = cargo_metadata::PackageBuilder::new(
+            "package".to_string(),
+            Version::parse("1.0.0").unwrap(),
+            PackageId {
+                repr: "pa".to_string(),
+            },
+            "/hoge".to_string(),
+        )
+        .build()
+        .unwrap();
+
+        let warning = Pattern(vec![Chunk::ViolationPackage(Severity::Warning)]);
+        let expected = format!("{} {}", "package v1.0.0".yellow(), "(/)");
+        assert_eq!(expected, warning.display(&package).to_string());
+
+        let info = Pattern(vec![Chunk::ViolationPackage(Severity::Info)]);
+        let expected = format!("{} {}", "package v1.0.0".blue(), "(/)");
+        assert_eq!(expected, info.display(&package).to_string());
+    }
 }
@@ -1,19 +1,44 @@
 use anyhow::{Error, anyhow};
 use cargo_metadata::{DependencyKind, Metadata, Package, PackageId};
+use cargo_platform::Platform;
 use petgraph::graph::NodeIndex;
 use petgraph::stable_graph::StableGraph;
 use petgraph::visit::Dfs;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+pub mod baseline;
+mod budget;
+mod cycle;
+mod duplicates;
+mod features;
 pub(crate) mod formatter;
+mod path;
 pub mod tree;
 pub mod violation;
 
 #[derive(Debug, Clone)]
 pub struct Graph {
-    pub graph: StableGraph<Package, DependencyKind>,
+    pub graph: StableGraph<Package, Edge>,
     pub nodes: HashMap<PackageId, NodeIndex>,
     pub root: Option<PackageId>,
+    pub workspace_members: HashSet<PackageId>,
+    /// Features enabled on each package by the resolver.
+    pub features: HashMap<PackageId, Vec<String>>,
+}
+
+/// A dependency of one kind between two packages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub kind: DependencyKind,
+    /// Target platforms the dependency is declared for, as in
+    /// `[target.'cfg(windows)'.dependencies]`. Empty when it is unconditional.
+    pub platforms: Vec<Platform>,
+}
+
+impl Edge {
+    pub fn is_conditional(&self) -> bool {
+        !self.platforms.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -42,6 +67,12 @@ pub fn build_dependency_graph(
         graph: StableGraph::new(),
         nodes: HashMap::new(),
         root: resolve.root.clone(),
+        workspace_members: metadata.workspace_members.iter().cloned().collect(),
+        features: resolve
+            .nodes
+            .iter()
+            .map(|node| (node.id.clone(), node.features.clone()))
+            .collect(),
     };
 
     for package in &metadata.packages {
@@ -62,20 +93,37 @@ pub fn build_dependency_graph(
             }
 
             // https://github.com/rust-lang/cargo/issues/7752
-            let mut kinds = vec![];
-            for kind in &dep.dep_kinds {
-                if !kinds.contains(&kind.kind) {
-                    kinds.push(kind.kind);
+            // One edge per kind: unconditional when any declaration of that
+            // kind is, otherwise conditional on all of the declared platforms.
+            let mut edges: Vec<(DependencyKind, Option<Vec<Platform>>)> = vec![];
+            for info in &dep.dep_kinds {
+                let index = match edges.iter().position(|(kind, _)| *kind == info.kind) {
+                    Some(index) => index,
+                    None => {
+                        edges.push((info.kind, Some(vec![])));
+                        edges.len() - 1
+                    }
+                };
+                let platforms = &mut edges[index].1;
+                match (&info.target, platforms.as_mut()) {
+                    (Some(target), Some(platforms)) => {
+                        if !platforms.contains(target) {
+                            platforms.push(target.clone());
+                        }
+                    }
+                    (None, _) => *platforms = None,
+                    (Some(_), None) => {}
                 }
             }
 
             let to = graph.nodes[&dep.pkg];
-            for kind in kinds {
+            for (kind, platforms) in edges {
                 if config.no_dev_dependencies && kind == DependencyKind::Development {
                     continue;
                 }
 
-                graph.graph.add_edge(from, to, kind);
+                let platforms = platforms.unwrap_or_default();
+                graph.graph.add_edge(from, to, Edge { kind, platforms });
             }
         }
     }
@@ -100,10 +148,30 @@ pub fn build_dependency_graph(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::metadata::{CollectMetadataConfig, collect_metadata};
     use anyhow::Result;
+    use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+
+    /// Builds the dependency graph of a demo workspace.
+    pub(crate) fn demo_graph(manifest_path: &str) -> Result<Graph> {
+        let metadata = collect_metadata(CollectMetadataConfig {
+            manifest_path: Some(manifest_path.to_string()),
+            ..CollectMetadataConfig::default()
+        })?;
+        build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())
+    }
+
+    /// The node of the package with the given name.
+    pub(crate) fn node(graph: &Graph, name: &str) -> NodeIndex {
+        graph
+            .nodes
+            .values()
+            .copied()
+            .find(|&node| graph.graph[node].name.as_str() == name)
+            .unwrap_or_else(|| panic!("package '{name}' is not in the graph"))
+    }
 
     fn clean_arch_metadata() -> Result<Metadata> {
         collect_metadata(CollectMetadataConfig {
@@ -166,4 +234,32 @@ mod tests {
         let config = DependencyGraphBuildConfigs::new(false);
         assert!(!config.no_dev_dependencies);
     }
+
+    #[test]
+    fn test_build_dependency_graph_keeps_edge_platforms() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/platforms/Cargo.toml")?;
+
+        let platforms = |name: &str| -> Vec<String> {
+            let edge = graph
+                .graph
+                .edge_references()
+                .find(|edge| graph.graph[edge.target()].name.as_str() == name)
+                .unwrap();
+            edge.weight()
+                .platforms
+                .iter()
+                .map(|platform| platform.to_string())
+                .collect()
+        };
+        assert!(platforms("plat-common").is_empty());
+        assert_eq!(
+            platforms("plat-web"),
+            vec![r#"cfg(target_arch = "wasm32")"#]
+        );
+        assert_eq!(
+            platforms("plat-native"),
+            vec![r#"cfg(not(target_arch = "wasm32"))"#]
+        );
+        Ok(())
+    }
 }
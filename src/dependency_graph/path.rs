@@ -0,0 +1,118 @@
+use super::{Edge, Graph};
+use cargo_metadata::DependencyKind;
+use petgraph::EdgeDirection;
+use petgraph::graph::NodeIndex;
+use petgraph::stable_graph::EdgeReference;
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, VecDeque};
+
+/// Shortest paths from some packages to every package reachable from them,
+/// found with a breadth-first walk.
+pub(crate) struct ShortestPaths<'a> {
+    graph: &'a Graph,
+    predecessors: HashMap<NodeIndex, (NodeIndex, DependencyKind)>,
+    reached: Vec<NodeIndex>,
+}
+
+/// Walks from all of `starts` at once through the edges accepted by
+/// `follow`. The starts themselves are never entered again.
+pub(crate) fn shortest_paths<'a>(
+    graph: &'a Graph,
+    starts: &[NodeIndex],
+    follow: impl Fn(EdgeReference<'_, Edge>) -> bool,
+) -> ShortestPaths<'a> {
+    let mut predecessors: HashMap<NodeIndex, (NodeIndex, DependencyKind)> = HashMap::new();
+    let mut reached = Vec::new();
+    let mut queue: VecDeque<NodeIndex> = starts.iter().copied().collect();
+
+    while let Some(current) = queue.pop_front() {
+        for edge in graph.graph.edges_directed(current, EdgeDirection::Outgoing) {
+            let next = edge.target();
+            if starts.contains(&next) || predecessors.contains_key(&next) || !follow(edge) {
+                continue;
+            }
+            predecessors.insert(next, (current, edge.weight().kind));
+            reached.push(next);
+            queue.push_back(next);
+        }
+    }
+
+    ShortestPaths {
+        graph,
+        predecessors,
+        reached,
+    }
+}
+
+impl ShortestPaths<'_> {
+    /// Reached packages, closest first. The starts are not included.
+    pub(crate) fn reached(&self) -> &[NodeIndex] {
+        &self.reached
+    }
+
+    /// Package names from the start leading to `node` to `node` itself,
+    /// along with the kind of the first edge. The path to a start is the
+    /// start alone.
+    pub(crate) fn path(&self, node: NodeIndex) -> (Vec<String>, DependencyKind) {
+        let mut path = vec![self.graph.graph[node].name.to_string()];
+        let mut node = node;
+        let mut dependency_kind = DependencyKind::Normal;
+        while let Some(&(previous, kind)) = self.predecessors.get(&node) {
+            path.push(self.graph.graph[previous].name.to_string());
+            dependency_kind = kind;
+            node = previous;
+        }
+        path.reverse();
+        (path, dependency_kind)
+    }
+
+    /// The start from which `node` was reached.
+    pub(crate) fn start_of(&self, mut node: NodeIndex) -> NodeIndex {
+        while let Some(&(previous, _)) = self.predecessors.get(&node) {
+            node = previous;
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::tests::{demo_graph, node};
+    use anyhow::Result;
+
+    #[test]
+    fn test_shortest_paths() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let interactor = node(&graph, "tangled-ca-interactor");
+        let database = node(&graph, "tangled-ca-database");
+
+        let paths = shortest_paths(&graph, &[interactor], |_| true);
+        assert_eq!(paths.reached().len(), 4);
+        assert_eq!(
+            paths.path(node(&graph, "tangled-ca-repositry")).0,
+            vec!["tangled-ca-interactor", "tangled-ca-repositry"]
+        );
+        assert_eq!(paths.start_of(database), interactor);
+
+        let paths = shortest_paths(&graph, &[interactor], |edge| {
+            edge.target() != node(&graph, "tangled-ca-repositry")
+        });
+        assert!(
+            !paths
+                .reached()
+                .contains(&node(&graph, "tangled-ca-repositry"))
+        );
+        assert_eq!(
+            paths.path(database),
+            (
+                vec![
+                    "tangled-ca-interactor".to_string(),
+                    "tangled-ca-database".to_string()
+                ],
+                DependencyKind::Normal
+            )
+        );
+        Ok(())
+    }
+}
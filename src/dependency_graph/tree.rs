@@ -1,8 +1,8 @@
 use crate::dependency_graph::formatter::Chunk;
-use crate::dependency_graph::violation::ViolationReport;
+use crate::dependency_graph::violation::{Violation, ViolationReport};
 
-use super::Graph;
 use super::formatter::Pattern;
+use super::{Edge, Graph};
 use anyhow::{Error, anyhow};
 use cargo_metadata::{DependencyKind, Metadata, Package, PackageId};
 use petgraph::EdgeDirection;
@@ -79,6 +79,34 @@ impl Default for TreePrintConfig {
     }
 }
 
+/// Rationale printed next to a violating edge, e.g. ` [reason; help: ...]`.
+fn violation_note(violation: &Violation) -> String {
+    let notes: Vec<String> = violation
+        .reason
+        .iter()
+        .cloned()
+        .chain(violation.help.iter().map(|help| format!("help: {help}")))
+        .collect();
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", notes.join("; "))
+    }
+}
+
+/// Platforms of a conditional edge, e.g. ` (cfg(windows))`.
+fn platform_note(edge: &Edge) -> String {
+    if !edge.is_conditional() {
+        return String::new();
+    }
+    let platforms: Vec<String> = edge
+        .platforms
+        .iter()
+        .map(|platform| platform.to_string())
+        .collect();
+    format!(" ({})", platforms.join(", "))
+}
+
 struct TreePrinter<'a, W: Write> {
     writer: W,
     graph: &'a Graph,
@@ -121,7 +149,7 @@ impl<'a, W: Write> TreePrinter<'a, W> {
 
     fn print_package(
         &mut self,
-        parent_package: Option<&'a Package>,
+        parent: Option<(&'a Package, &'a Edge)>,
         package: &'a Package,
     ) -> Result<(), Error> {
         let new = self.all || self.visited_deps.insert(&package.id);
@@ -147,17 +175,32 @@ impl<'a, W: Write> TreePrinter<'a, W> {
         }
 
         let star = if new { "" } else { " (*)" };
-        let is_violation = if let Some(parent) = parent_package {
-            self.report.is_violation(&parent.name, &package.name)
-        } else {
-            false
-        };
-        match is_violation {
-            true => {
-                let f = Pattern(vec![Chunk::ViolationPackage]);
-                writeln!(self.writer, "{}{}", f.display(package), star)?;
+        let platforms = parent
+            .map(|(_, edge)| platform_note(edge))
+            .unwrap_or_default();
+        let violation = parent.and_then(|(parent, edge)| {
+            self.report
+                .edge_violation(&parent.name, &package.name, edge.kind)
+        });
+        match violation {
+            Some(violation) => {
+                let f = Pattern(vec![Chunk::ViolationPackage(violation.severity)]);
+                writeln!(
+                    self.writer,
+                    "{}{}{}{}",
+                    f.display(package),
+                    platforms,
+                    star,
+                    violation_note(violation)
+                )?;
             }
-            false => writeln!(self.writer, "{}{}", self.format.display(package), star)?,
+            None => writeln!(
+                self.writer,
+                "{}{}{}",
+                self.format.display(package),
+                platforms,
+                star
+            )?,
         };
 
         if !new {
@@ -183,8 +226,8 @@ impl<'a, W: Write> TreePrinter<'a, W> {
         let idx = self.graph.nodes[&package.id];
         let mut deps = vec![];
         for edge in self.graph.graph.edges_directed(idx, self.direction) {
-            let weight: &DependencyKind = edge.weight();
-            if *weight != kind {
+            let weight: &Edge = edge.weight();
+            if weight.kind != kind {
                 continue;
             }
 
@@ -192,7 +235,7 @@ impl<'a, W: Write> TreePrinter<'a, W> {
                 EdgeDirection::Incoming => &self.graph.graph[edge.source()],
                 EdgeDirection::Outgoing => &self.graph.graph[edge.target()],
             };
-            deps.push(dep);
+            deps.push((dep, weight));
         }
 
         if deps.is_empty() {
@@ -200,7 +243,7 @@ impl<'a, W: Write> TreePrinter<'a, W> {
         }
 
         // ensure a consistent output ordering
-        deps.sort_by_key(|p| &p.id);
+        deps.sort_by_key(|(p, _)| &p.id);
 
         let name = match kind {
             DependencyKind::Normal => None,
@@ -221,9 +264,9 @@ impl<'a, W: Write> TreePrinter<'a, W> {
         }
 
         let mut it = deps.iter().peekable();
-        while let Some(dependency) = it.next() {
+        while let Some((dependency, edge)) = it.next() {
             self.levels_continue.push(it.peek().is_some());
-            self.print_package(Some(package), dependency)?;
+            self.print_package(Some((package, edge)), dependency)?;
             self.levels_continue.pop();
         }
 
@@ -436,4 +479,67 @@ mod tests {
         assert!(matches!(config.charset, Charset::Utf8));
         assert!(matches!(config.prefix, Prefix::Indent));
     }
+
+    #[test]
+    fn test_print_violation_reason_next_to_edge() -> Result<()> {
+        let config = CollectMetadataConfig {
+            manifest_path: Some("tests/demo_crates/tangled-clean-arch/Cargo.toml".to_string()),
+            ..CollectMetadataConfig::default()
+        };
+        let metadata = collect_metadata(config)?;
+        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
+        let rules = DependencyRules::from_file("tests/test_files/reason_rules_test.toml")?;
+        let report = check_violations(&graph, &rules);
+
+        let mut buf = Vec::new();
+        print(
+            &mut buf,
+            &graph,
+            &metadata,
+            &report,
+            TreePrintConfig::default(),
+        )?;
+
+        let output = String::from_utf8(buf)?;
+        let line = output
+            .lines()
+            .find(|line| line.contains("[The domain core"))
+            .expect("violating edge should carry the rule reason");
+        assert!(line.contains("tangled-ca-"));
+        assert!(line.contains("; help: Define a port trait"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_platforms_next_to_conditional_edges() -> Result<()> {
+        let config = CollectMetadataConfig {
+            manifest_path: Some("tests/demo_crates/platforms/Cargo.toml".to_string()),
+            ..CollectMetadataConfig::default()
+        };
+        let metadata = collect_metadata(config)?;
+        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
+        let report = check_violations(&graph, &DependencyRules::default());
+
+        let mut buf = Vec::new();
+        print(
+            &mut buf,
+            &graph,
+            &metadata,
+            &report,
+            TreePrintConfig::default(),
+        )?;
+
+        let output = String::from_utf8(buf)?;
+        let line = |name: &str| {
+            output
+                .lines()
+                .find(|line| line.contains(&format!("── {name} ")))
+                .unwrap()
+                .to_string()
+        };
+        assert!(line("plat-web").ends_with(r#" (cfg(target_arch = "wasm32"))"#));
+        assert!(line("plat-native").ends_with(r#" (cfg(not(target_arch = "wasm32")))"#));
+        assert!(!line("plat-common").contains("cfg("));
+        Ok(())
+    }
 }
@@ -1,74 +1,1070 @@
 use super::Graph;
-use crate::dependency_rule::DependencyRules;
+use super::budget::dependency_usage;
+use super::cycle::find_cycles;
+use super::duplicates::find_duplicate_versions;
+use super::features::{member_features, requested_feature};
+use super::path::shortest_paths;
+use crate::dependency_rule::{
+    Budget, DependencyMatcher, DependencyRule, DependencyRules, Exception, Facade, Layer,
+    LicensePolicy, LicenseProblem, PackageSource, Severity,
+};
+use anyhow::Error;
+use cargo_metadata::{Dependency, DependencyKind, Package};
+use chrono::{Local, NaiveDate};
+use petgraph::graph::NodeIndex;
 use petgraph::visit::{EdgeRef, IntoEdgeReferences};
-use std::collections::HashSet;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::io::Write;
+use std::mem;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Violation {
     pub parent: String,
     pub dependency: String,
+    /// Package names from `parent` to `dependency`, both inclusive.
+    pub path: Vec<String>,
+    /// Kind of the first edge of `path`.
+    pub dependency_kind: DependencyKind,
+    pub kind: ViolationKind,
+    pub severity: Severity,
+    /// Why the rule exists, copied from the rule that was violated.
+    pub reason: Option<String>,
+    /// How to fix the violation, copied from the rule that was violated.
+    pub help: Option<String>,
+    /// Rules file that defines the violated rule.
+    pub origin: Option<PathBuf>,
+    /// Listed in the baseline, so it does not fail the check.
+    pub known: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    /// The dependency matches a rule's `forbidden_dependencies`.
+    Forbidden,
+    /// The resolved version of the dependency is in a forbidden range.
+    ForbiddenVersion {
+        requirement: VersionReq,
+        resolved: Version,
+    },
+    /// The resolved version of the dependency is outside a required range.
+    RequiredVersion {
+        requirement: VersionReq,
+        resolved: Version,
+    },
+    /// The dependency is missing from a rule's `allowed_dependencies`.
+    NotAllowed,
+    /// The dependency belongs to a higher layer than its parent.
+    Layer { from: String, to: String },
+    /// A strict layer depends on a layer below the one directly beneath it.
+    SkippedLayer { from: String, to: String },
+    /// `path` reaches a facade's target without going through the facade.
+    BypassedFacade { facades: Vec<String> },
+    /// `path` is a dependency cycle while `no_cycles` is set.
+    Cycle,
+    /// The package depends on more crates than its budget allows.
+    Budget {
+        scope: BudgetScope,
+        count: usize,
+        limit: usize,
+        /// Direct dependencies bringing in the most crates, with their counts.
+        contributors: Vec<(String, usize)>,
+    },
+    /// The crate is resolved to more semver-incompatible versions than allowed.
+    DuplicateVersions {
+        /// Each version with the workspace members that pull it in.
+        versions: Vec<(Version, Vec<String>)>,
+        incompatible: usize,
+    },
+    /// The dependency declares no license while `[licenses]` has an allow-list.
+    MissingLicense,
+    /// The dependency's license is not a valid SPDX expression.
+    InvalidLicense { expression: String },
+    /// The dependency can only be used under a license listed in `deny`.
+    DeniedLicense { expression: String, license: String },
+    /// A forbidden feature is enabled on the dependency.
+    ForbiddenFeature {
+        feature: String,
+        /// The package that turns the feature on and the feature it requests.
+        enabled_by: (String, String),
+    },
+    /// The dependency's license cannot be satisfied with the allowed licenses.
+    LicenseNotAllowed { expression: String },
+    /// The dependency is declared without `default-features = false`.
+    DefaultFeaturesEnabled {
+        /// Manifest declaring the dependency.
+        manifest: PathBuf,
+        /// The dependency entry, including the table it is declared in.
+        entry: String,
+    },
+    /// The dependency comes from a source listed in `forbid_sources`.
+    ForbiddenSource { source: String },
+    /// The dependency comes from a source missing from `sources`.
+    SourceNotAllowed { source: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetScope {
+    Direct,
+    Transitive,
+}
+
+impl fmt::Display for BudgetScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetScope::Direct => f.write_str("direct"),
+            BudgetScope::Transitive => f.write_str("transitive"),
+        }
+    }
+}
+
+impl ViolationKind {
+    /// A short name of the kind of problem, which stays the same when its
+    /// details such as the resolved version change.
+    pub(crate) fn id(&self) -> String {
+        match self {
+            ViolationKind::Forbidden => "forbidden".to_string(),
+            ViolationKind::ForbiddenVersion { .. } => "forbidden-version".to_string(),
+            ViolationKind::RequiredVersion { .. } => "required-version".to_string(),
+            ViolationKind::NotAllowed => "not-allowed".to_string(),
+            ViolationKind::Layer { .. } => "layer".to_string(),
+            ViolationKind::SkippedLayer { .. } => "skipped-layer".to_string(),
+            ViolationKind::BypassedFacade { .. } => "bypassed-facade".to_string(),
+            ViolationKind::Cycle => "cycle".to_string(),
+            ViolationKind::Budget { scope, .. } => format!("{scope}-budget"),
+            ViolationKind::DuplicateVersions { .. } => "duplicate-versions".to_string(),
+            ViolationKind::MissingLicense => "missing-license".to_string(),
+            ViolationKind::InvalidLicense { .. } => "invalid-license".to_string(),
+            ViolationKind::DeniedLicense { .. } => "denied-license".to_string(),
+            ViolationKind::ForbiddenFeature { feature, .. } => {
+                format!("forbidden-feature:{feature}")
+            }
+            ViolationKind::LicenseNotAllowed { .. } => "license-not-allowed".to_string(),
+            ViolationKind::DefaultFeaturesEnabled { .. } => "default-features".to_string(),
+            ViolationKind::ForbiddenSource { .. } => "forbidden-source".to_string(),
+            ViolationKind::SourceNotAllowed { .. } => "source-not-allowed".to_string(),
+        }
+    }
+}
+
+/// The problem a violation reports: its kind on an edge, or on a package for
+/// budgets and duplicate versions. Only one violation is kept per subject.
+type Subject = (String, String, DependencyKind, String);
+
+/// An edge of the graph, by the names of its packages and its kind.
+type EdgeKey = (String, String, DependencyKind);
+
+impl Violation {
+    fn subject(&self) -> Subject {
+        (
+            self.parent.clone(),
+            self.dependency.clone(),
+            self.dependency_kind,
+            self.kind.id(),
+        )
+    }
+
+    /// The first edge of the path, which is the one highlighted in the tree.
+    fn first_edge(&self) -> Option<EdgeKey> {
+        match self.path.as_slice() {
+            [from, to, ..] => Some((from.clone(), to.clone(), self.dependency_kind)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.join(" -> "))?;
+        match self.dependency_kind {
+            DependencyKind::Build => write!(f, " (build-dependency)")?,
+            DependencyKind::Development => write!(f, " (dev-dependency)")?,
+            _ => {}
+        }
+        write!(f, ": ")?;
+        match &self.kind {
+            ViolationKind::Forbidden => write!(f, "forbidden dependency"),
+            ViolationKind::ForbiddenVersion {
+                requirement,
+                resolved,
+            } => write!(
+                f,
+                "resolved version {resolved} is in forbidden range '{requirement}'"
+            ),
+            ViolationKind::RequiredVersion {
+                requirement,
+                resolved,
+            } => write!(
+                f,
+                "resolved version {resolved} does not satisfy required range '{requirement}'"
+            ),
+            ViolationKind::NotAllowed => write!(f, "dependency is not allowed"),
+            ViolationKind::Layer { from, to } => {
+                write!(f, "layer '{from}' must not depend on higher layer '{to}'")
+            }
+            ViolationKind::SkippedLayer { from, to } => write!(
+                f,
+                "strict layer '{from}' may only depend on the layer directly below it, not '{to}'"
+            ),
+            ViolationKind::BypassedFacade { facades } => match facades.as_slice() {
+                [facade] => write!(f, "path bypasses facade '{facade}'"),
+                _ => write!(f, "path bypasses facades '{}'", facades.join("', '")),
+            },
+            ViolationKind::Cycle => write!(f, "dependency cycle"),
+            ViolationKind::Budget {
+                scope,
+                count,
+                limit,
+                contributors,
+            } => {
+                write!(
+                    f,
+                    "{count} {scope} dependencies exceed the budget of {limit}"
+                )?;
+                if !contributors.is_empty() {
+                    let contributors: Vec<String> = contributors
+                        .iter()
+                        .map(|(name, count)| format!("{name} ({count})"))
+                        .collect();
+                    write!(f, "; biggest contributors: {}", contributors.join(", "))?;
+                }
+                Ok(())
+            }
+            ViolationKind::DuplicateVersions {
+                versions,
+                incompatible,
+            } => {
+                let versions: Vec<String> = versions
+                    .iter()
+                    .map(|(version, members)| {
+                        if members.is_empty() {
+                            version.to_string()
+                        } else {
+                            format!("{version} (via {})", members.join(", "))
+                        }
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "{incompatible} incompatible versions: {}",
+                    versions.join(", ")
+                )
+            }
+            ViolationKind::ForbiddenFeature {
+                feature,
+                enabled_by,
+            } => {
+                let (package, requested) = enabled_by;
+                write!(f, "feature '{feature}' is forbidden, enabled by {package}")?;
+                if requested != feature {
+                    write!(f, " through feature '{requested}'")?;
+                }
+                Ok(())
+            }
+            ViolationKind::DefaultFeaturesEnabled { manifest, entry } => write!(
+                f,
+                "{entry} of '{}' must set default-features = false",
+                manifest.display()
+            ),
+            ViolationKind::MissingLicense => write!(f, "no license is declared"),
+            ViolationKind::InvalidLicense { expression } => {
+                write!(f, "license '{expression}' is not a valid SPDX expression")
+            }
+            ViolationKind::DeniedLicense {
+                expression,
+                license,
+            } => write!(
+                f,
+                "license '{expression}' requires denied license '{license}'"
+            ),
+            ViolationKind::LicenseNotAllowed { expression } => {
+                write!(f, "license '{expression}' is not allowed")
+            }
+            ViolationKind::ForbiddenSource { source } => {
+                write!(f, "dependency from forbidden source '{source}'")
+            }
+            ViolationKind::SourceNotAllowed { source } => {
+                write!(f, "dependency source '{source}' is not allowed")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct ViolationReport {
     pub violations: Vec<Violation>,
-    violated_edges: HashSet<(String, String)>,
+    /// Problems with `[[exceptions]]`, such as expired or unused entries.
+    pub exception_warnings: Vec<String>,
+    /// Index of the most severe violation recorded for each edge of the graph.
+    violated_edges: HashMap<EdgeKey, usize>,
+    /// Index of the violation recorded for each subject.
+    subjects: HashMap<Subject, usize>,
 }
 
 impl ViolationReport {
     pub fn is_violation(&self, parent_name: &str, dependency_name: &str) -> bool {
         self.violated_edges
-            .contains(&(parent_name.to_string(), dependency_name.to_string()))
+            .keys()
+            .any(|(parent, dependency, _)| parent == parent_name && dependency == dependency_name)
+    }
+
+    pub fn is_violation_of_kind(
+        &self,
+        parent_name: &str,
+        dependency_name: &str,
+        kind: DependencyKind,
+    ) -> bool {
+        self.violation_severity(parent_name, dependency_name, kind)
+            .is_some()
+    }
+
+    pub fn violation_severity(
+        &self,
+        parent_name: &str,
+        dependency_name: &str,
+        kind: DependencyKind,
+    ) -> Option<Severity> {
+        self.edge_violation(parent_name, dependency_name, kind)
+            .map(|violation| violation.severity)
+    }
+
+    /// Returns the most severe violation highlighted on the given edge.
+    pub fn edge_violation(
+        &self,
+        parent_name: &str,
+        dependency_name: &str,
+        kind: DependencyKind,
+    ) -> Option<&Violation> {
+        self.violated_edges
+            .get(&(parent_name.to_string(), dependency_name.to_string(), kind))
+            .map(|&index| &self.violations[index])
     }
 
     pub fn has_violations(&self) -> bool {
         !self.violations.is_empty()
     }
+
+    /// Highest severity among violations that are not in the baseline.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.violations
+            .iter()
+            .filter(|v| !v.known)
+            .map(|v| v.severity)
+            .max()
+    }
+
+    /// Records a violation and marks the first edge of its path as violated.
+    /// Only one violation of a kind on the same edge is kept: the first one,
+    /// unless a later one is more severe.
+    fn add(&mut self, violation: Violation) {
+        let index = match self.subjects.entry(violation.subject()) {
+            Entry::Occupied(entry) => {
+                let index = *entry.get();
+                if self.violations[index].severity >= violation.severity {
+                    return;
+                }
+                let replaced = mem::replace(&mut self.violations[index], violation);
+                // a path to the same package may start with another edge
+                let edge = replaced.first_edge();
+                if edge != self.violations[index].first_edge() {
+                    if let Some(edge) = edge {
+                        self.unmark_edge(edge, index);
+                    }
+                }
+                index
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(self.violations.len());
+                self.violations.push(violation);
+                self.violations.len() - 1
+            }
+        };
+        self.mark_edge(index);
+    }
+
+    /// Marks the first edge of the violation at `index`, unless a more
+    /// severe or an earlier equally severe violation already marks it.
+    fn mark_edge(&mut self, index: usize) {
+        let Some(edge) = self.violations[index].first_edge() else {
+            return;
+        };
+        let severity = self.violations[index].severity;
+        match self.violated_edges.entry(edge) {
+            Entry::Occupied(mut entry) => {
+                let marked = *entry.get();
+                let marked_severity = self.violations[marked].severity;
+                if marked_severity < severity || (marked_severity == severity && index < marked) {
+                    entry.insert(index);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+    }
+
+    /// Marks `edge` again with the violations still starting with it, when
+    /// the violation at `index` that marked it moved to another edge.
+    fn unmark_edge(&mut self, edge: EdgeKey, index: usize) {
+        if self.violated_edges.get(&edge) != Some(&index) {
+            return;
+        }
+        self.violated_edges.remove(&edge);
+        let remaining: Vec<usize> = (0..self.violations.len())
+            .filter(|&other| self.violations[other].first_edge().as_ref() == Some(&edge))
+            .collect();
+        for other in remaining {
+            self.mark_edge(other);
+        }
+    }
 }
 
 #[tracing::instrument(skip_all)]
 pub fn check_violations(graph: &Graph, rules: &DependencyRules) -> ViolationReport {
-    let mut violations = Vec::new();
-    let mut violated_edges = HashSet::new();
+    check_violations_at(graph, rules, Local::now().date_naive())
+}
+
+/// Checks the graph as of `today`, which decides whether exceptions expired.
+pub fn check_violations_at(
+    graph: &Graph,
+    rules: &DependencyRules,
+    today: NaiveDate,
+) -> ViolationReport {
+    let mut report = ViolationReport::default();
+    let mut exemptions = Exemptions::new(&rules.exceptions, today);
 
     for edge in graph.graph.edge_references() {
         let parent = &graph.graph[edge.source()];
         let child = &graph.graph[edge.target()];
+        let dependency_kind = edge.weight().kind;
+        let path = vec![parent.name.clone(), child.name.clone()];
+        let source = PackageSource::of(child, graph.workspace_members.contains(&child.id));
 
-        let is_forbidden = rules.rules.iter().any(|rule| {
-            rule.package == parent.name && rule.forbidden_dependencies.contains(&child.name)
-        });
+        let mut violations: Vec<_> = rules
+            .rules
+            .iter()
+            .filter(|rule| rule.package.matches(parent) && rule.applies_to(edge.weight()))
+            .filter_map(|rule| {
+                rule_violation(rule, parent, child, dependency_kind, &source)
+                    .map(|kind| (kind, rule.severity, Some(rule), &rule.origin))
+            })
+            .collect();
+        violations.extend(
+            layer_violation(rules, parent, child)
+                .map(|(kind, layer)| (kind, layer.severity, None, &layer.origin)),
+        );
+        if !violations.is_empty() && exemptions.exempts(parent, child) {
+            continue;
+        }
 
-        if is_forbidden && violated_edges.insert((parent.name.clone(), child.name.clone())) {
-            violations.push(Violation {
+        for (kind, severity, rule, origin) in violations {
+            report.add(Violation {
                 parent: parent.name.clone(),
                 dependency: child.name.clone(),
+                path: path.clone(),
+                dependency_kind,
+                kind,
+                severity,
+                reason: rule.and_then(|rule| rule.reason.clone()),
+                help: rule.and_then(|rule| rule.help.clone()),
+                origin: origin.clone(),
+                known: false,
             });
         }
     }
 
-    ViolationReport {
-        violations,
-        violated_edges,
+    let members = sorted_by_name(
+        graph,
+        graph
+            .workspace_members
+            .iter()
+            .filter_map(|id| graph.nodes.get(id).copied())
+            .collect(),
+    );
+    for rule in rules
+        .rules
+        .iter()
+        .filter(|rule| !rule.forbidden_features.is_empty())
+    {
+        for &start in &members {
+            if rule.package.matches(&graph.graph[start]) {
+                check_features(graph, rule, start, &mut exemptions, &mut report);
+            }
+        }
+    }
+
+    let packages = sorted_by_name(graph, graph.nodes.values().copied().collect());
+    for rule in rules.rules.iter().filter(|rule| rule.transitive) {
+        for &start in &packages {
+            if rule.package.matches(&graph.graph[start]) {
+                check_transitive(graph, rule, start, &mut exemptions, &mut report);
+            }
+        }
+    }
+
+    if let Some(no_cycles) = &rules.no_cycles {
+        for cycle in find_cycles(graph, &no_cycles.kinds) {
+            let packages: Vec<&Package> =
+                cycle.nodes.iter().map(|node| &graph.graph[*node]).collect();
+            if packages
+                .windows(2)
+                .any(|pair| exemptions.exempts(pair[0], pair[1]))
+            {
+                continue;
+            }
+
+            // cycles starting at the same package differ in their first edge
+            report.add(Violation {
+                parent: packages[0].name.to_string(),
+                dependency: packages[1].name.to_string(),
+                path: packages
+                    .iter()
+                    .map(|package| package.name.to_string())
+                    .collect(),
+                dependency_kind: cycle.kinds[0],
+                kind: ViolationKind::Cycle,
+                severity: no_cycles.severity,
+                reason: no_cycles.reason.clone(),
+                help: None,
+                origin: no_cycles.origin.clone(),
+                known: false,
+            });
+        }
+    }
+
+    for budget in &rules.budgets {
+        check_budget(graph, budget, &mut report);
+    }
+
+    for facade in &rules.facades {
+        check_facade(graph, facade, &mut exemptions, &mut report);
+    }
+
+    if let Some(no_duplicates) = &rules.no_duplicate_versions {
+        for duplicate in find_duplicate_versions(graph) {
+            let skipped = graph
+                .nodes
+                .values()
+                .map(|node| &graph.graph[*node])
+                .any(|package| package.name == duplicate.name && no_duplicates.is_skipped(package));
+            if skipped || duplicate.incompatible <= no_duplicates.allowed_versions(&duplicate.name)
+            {
+                continue;
+            }
+
+            report.add(Violation {
+                parent: duplicate.name.clone(),
+                dependency: duplicate.name.clone(),
+                path: vec![duplicate.name],
+                dependency_kind: DependencyKind::Normal,
+                kind: ViolationKind::DuplicateVersions {
+                    versions: duplicate.versions,
+                    incompatible: duplicate.incompatible,
+                },
+                severity: no_duplicates.severity,
+                reason: no_duplicates.reason.clone(),
+                help: None,
+                origin: no_duplicates.origin.clone(),
+                known: false,
+            });
+        }
+    }
+
+    if let Some(licenses) = &rules.licenses {
+        check_licenses(graph, licenses, &mut exemptions, &mut report);
+    }
+
+    report.exception_warnings = exemptions.warnings();
+    report
+}
+
+/// Orders packages by name and version, so that the checks walking from
+/// them report violations in the same order on every run.
+fn sorted_by_name(graph: &Graph, mut nodes: Vec<NodeIndex>) -> Vec<NodeIndex> {
+    nodes.sort_by(|a, b| {
+        let (a, b) = (&graph.graph[*a], &graph.graph[*b]);
+        a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version))
+    });
+    nodes
+}
+
+/// Checks every package reachable from the workspace members against the
+/// license policy. A breadth-first walk from all members at once finds the
+/// shortest path bringing each package in.
+fn check_licenses(
+    graph: &Graph,
+    licenses: &LicensePolicy,
+    exemptions: &mut Exemptions<'_>,
+    report: &mut ViolationReport,
+) {
+    let mut members: Vec<NodeIndex> = graph
+        .workspace_members
+        .iter()
+        .filter_map(|id| graph.nodes.get(id).copied())
+        .collect();
+    members.sort_by(|a, b| graph.graph[*a].name.cmp(&graph.graph[*b].name));
+
+    let paths = shortest_paths(graph, &members, |_| true);
+    for &node in paths.reached() {
+        let package = &graph.graph[node];
+        let Some(problem) = licenses.check(package) else {
+            continue;
+        };
+        let member = &graph.graph[paths.start_of(node)];
+        if exemptions.exempts(member, package) {
+            continue;
+        }
+
+        let (path, dependency_kind) = paths.path(node);
+        report.add(Violation {
+            parent: member.name.clone(),
+            dependency: package.name.clone(),
+            path,
+            dependency_kind,
+            kind: license_violation(problem),
+            severity: licenses.severity,
+            reason: licenses.reason.clone(),
+            help: None,
+            origin: licenses.origin.clone(),
+            known: false,
+        });
+    }
+}
+
+fn license_violation(problem: LicenseProblem) -> ViolationKind {
+    match problem {
+        LicenseProblem::Missing => ViolationKind::MissingLicense,
+        LicenseProblem::Invalid { expression } => ViolationKind::InvalidLicense { expression },
+        LicenseProblem::Denied {
+            expression,
+            license,
+        } => ViolationKind::DeniedLicense {
+            expression,
+            license,
+        },
+        LicenseProblem::NotAllowed { expression } => {
+            ViolationKind::LicenseNotAllowed { expression }
+        }
+    }
+}
+
+/// Counts the dependencies of every workspace member the budget applies to.
+fn check_budget(graph: &Graph, budget: &Budget, report: &mut ViolationReport) {
+    let mut members: Vec<&NodeIndex> = graph
+        .workspace_members
+        .iter()
+        .filter_map(|id| graph.nodes.get(id))
+        .filter(|&&node| budget.package.matches(&graph.graph[node]))
+        .collect();
+    members.sort_by(|a, b| graph.graph[**a].name.cmp(&graph.graph[**b].name));
+
+    for &node in members {
+        let usage = dependency_usage(graph, node, &budget.kinds);
+        let scopes = [
+            (BudgetScope::Direct, usage.direct, budget.max_direct),
+            (
+                BudgetScope::Transitive,
+                usage.transitive,
+                budget.max_transitive,
+            ),
+        ];
+        for (scope, count, limit) in scopes {
+            let Some(limit) = limit.filter(|&limit| count > limit) else {
+                continue;
+            };
+            let name = graph.graph[node].name.to_string();
+            report.add(Violation {
+                parent: name.clone(),
+                dependency: name.clone(),
+                path: vec![name],
+                dependency_kind: DependencyKind::Normal,
+                kind: ViolationKind::Budget {
+                    scope,
+                    count,
+                    limit,
+                    contributors: usage.contributors.clone(),
+                },
+                severity: budget.severity,
+                reason: budget.reason.clone(),
+                help: None,
+                origin: budget.origin.clone(),
+                known: false,
+            });
+        }
+    }
+}
+
+/// Walks the dependencies of every workspace member the facade applies to
+/// without entering the facades, so that each target still reached is
+/// reported with the shortest path bypassing them.
+fn check_facade(
+    graph: &Graph,
+    facade: &Facade,
+    exemptions: &mut Exemptions<'_>,
+    report: &mut ViolationReport,
+) {
+    let mut members: Vec<NodeIndex> = graph
+        .workspace_members
+        .iter()
+        .filter_map(|id| graph.nodes.get(id).copied())
+        .filter(|&node| {
+            let package = &graph.graph[node];
+            facade.package.matches(package)
+                && !facade.target.matches(package)
+                && !facade.is_facade(package)
+        })
+        .collect();
+    members.sort_by(|a, b| graph.graph[*a].name.cmp(&graph.graph[*b].name));
+    let facades: Vec<String> = facade
+        .only_via
+        .iter()
+        .map(|matcher| matcher.to_string())
+        .collect();
+
+    for start in members {
+        // paths going on through a target are reported at the target
+        let paths = shortest_paths(graph, &[start], |edge| {
+            facade.kinds.contains(&edge.weight().kind)
+                && !facade.target.matches(&graph.graph[edge.source()])
+                && !facade.is_facade(&graph.graph[edge.target()])
+        });
+        for &node in paths.reached() {
+            let package = &graph.graph[node];
+            if !facade.target.matches(package) || exemptions.exempts(&graph.graph[start], package) {
+                continue;
+            }
+
+            let (path, dependency_kind) = paths.path(node);
+            report.add(Violation {
+                parent: graph.graph[start].name.clone(),
+                dependency: package.name.clone(),
+                path,
+                dependency_kind,
+                kind: ViolationKind::BypassedFacade {
+                    facades: facades.clone(),
+                },
+                severity: facade.severity,
+                reason: facade.reason.clone(),
+                help: None,
+                origin: facade.origin.clone(),
+                known: false,
+            });
+        }
     }
 }
 
+/// Exceptions in effect during a check, remembering which of them were used.
+struct Exemptions<'a> {
+    exceptions: &'a [Exception],
+    today: NaiveDate,
+    used: Vec<bool>,
+}
+
+impl<'a> Exemptions<'a> {
+    fn new(exceptions: &'a [Exception], today: NaiveDate) -> Self {
+        Self {
+            exceptions,
+            today,
+            used: vec![false; exceptions.len()],
+        }
+    }
+
+    /// Returns whether an unexpired exception covers the violating edge.
+    fn exempts(&mut self, package: &Package, dependency: &Package) -> bool {
+        let mut exempt = false;
+        for (exception, used) in self.exceptions.iter().zip(&mut self.used) {
+            if !exception.is_expired(self.today) && exception.matches(package, dependency) {
+                *used = true;
+                exempt = true;
+            }
+        }
+        exempt
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        self.exceptions
+            .iter()
+            .zip(&self.used)
+            .filter_map(|(exception, used)| {
+                if exception.is_expired(self.today) {
+                    Some(format!(
+                        "exception for {exception} expired on {}: {}",
+                        exception.expires, exception.reason
+                    ))
+                } else if !used {
+                    Some(format!(
+                        "exception for {exception} does not match any violation and can be removed"
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn rule_violation(
+    rule: &DependencyRule,
+    package: &Package,
+    dependency: &Package,
+    kind: DependencyKind,
+    source: &PackageSource<'_>,
+) -> Option<ViolationKind> {
+    if let Some(matcher) = rule.forbidden_match(dependency) {
+        Some(forbidden_violation(matcher, dependency))
+    } else if let Some(requirement) = rule.unmet_version_requirement(dependency) {
+        Some(ViolationKind::RequiredVersion {
+            requirement: requirement.clone(),
+            resolved: dependency.version.clone(),
+        })
+    } else if !rule.is_allowed(dependency) {
+        Some(ViolationKind::NotAllowed)
+    } else if rule.is_forbidden_source(source) {
+        Some(ViolationKind::ForbiddenSource {
+            source: source.to_string(),
+        })
+    } else if !rule.is_allowed_source(source) {
+        Some(ViolationKind::SourceNotAllowed {
+            source: source.to_string(),
+        })
+    } else {
+        rule.default_features_declaration(package, dependency, kind)
+            .map(|declaration| ViolationKind::DefaultFeaturesEnabled {
+                manifest: package.manifest_path.clone().into_std_path_buf(),
+                entry: dependency_entry(declaration),
+            })
+    }
+}
+
+/// The manifest entry of a declared dependency, e.g.
+/// `serde` in `[target.'cfg(windows)'.dependencies]`.
+fn dependency_entry(declaration: &Dependency) -> String {
+    let table = match declaration.kind {
+        DependencyKind::Build => "build-dependencies",
+        DependencyKind::Development => "dev-dependencies",
+        _ => "dependencies",
+    };
+    let name = declaration.rename.as_deref().unwrap_or(&declaration.name);
+    match &declaration.target {
+        Some(target) => format!("`{name}` in [target.'{target}'.{table}]"),
+        None => format!("`{name}` in [{table}]"),
+    }
+}
+
+fn forbidden_violation(matcher: &DependencyMatcher, dependency: &Package) -> ViolationKind {
+    match &matcher.version {
+        Some(requirement) => ViolationKind::ForbiddenVersion {
+            requirement: requirement.clone(),
+            resolved: dependency.version.clone(),
+        },
+        None => ViolationKind::Forbidden,
+    }
+}
+
+/// Checks whether an edge points from a lower layer to a higher one, or skips
+/// a layer below a strict layer. Packages outside every layer are ignored.
+fn layer_violation<'a>(
+    rules: &'a DependencyRules,
+    parent: &Package,
+    child: &Package,
+) -> Option<(ViolationKind, &'a Layer)> {
+    let from = rules.layer_of(parent)?;
+    let to = rules.layer_of(child)?;
+    let from_name = rules.layers[from].name.clone();
+    let to_name = rules.layers[to].name.clone();
+
+    let kind = if to < from {
+        ViolationKind::Layer {
+            from: from_name,
+            to: to_name,
+        }
+    } else if rules.layers[from].strict && to > from + 1 {
+        ViolationKind::SkippedLayer {
+            from: from_name,
+            to: to_name,
+        }
+    } else {
+        return None;
+    };
+    Some((kind, &rules.layers[from]))
+}
+
+/// Looks for forbidden features on every package reachable from `start`
+/// through edges the rule applies to. The violation path leads to the
+/// closest package that turns the feature on.
+fn check_features(
+    graph: &Graph,
+    rule: &DependencyRule,
+    start: NodeIndex,
+    exemptions: &mut Exemptions<'_>,
+    report: &mut ViolationReport,
+) {
+    let paths = shortest_paths(graph, &[start], |edge| rule.applies_to(edge.weight()));
+    let enabled_features = member_features(graph, start, |edge| rule.applies_to(edge));
+
+    for &node in paths.reached() {
+        let package = &graph.graph[node];
+        let Some(features) = rule.forbidden_features.get(package.name.as_str()) else {
+            continue;
+        };
+        let Some(enabled) = enabled_features.get(&node) else {
+            continue;
+        };
+        let Some(feature) = features.iter().find(|feature| enabled.contains(*feature)) else {
+            continue;
+        };
+
+        let enabler = [start]
+            .iter()
+            .chain(paths.reached())
+            .filter(|&&dependent| {
+                graph
+                    .graph
+                    .edges_connecting(dependent, node)
+                    .any(|edge| rule.applies_to(edge.weight()))
+            })
+            .find_map(|&dependent| {
+                requested_feature(graph, &enabled_features, dependent, node, feature)
+                    .map(|requested| (dependent, requested))
+            });
+        // features only turned on outside the member's dependencies are
+        // left to the rules of the members that turn them on
+        let Some((dependent, requested)) = enabler else {
+            continue;
+        };
+        if exemptions.exempts(&graph.graph[start], package) {
+            continue;
+        }
+
+        let (mut path, dependency_kind) = paths.path(dependent);
+        path.push(package.name.clone());
+        let dependency_kind = if dependent == start {
+            graph
+                .graph
+                .edges_connecting(start, node)
+                .map(|edge| edge.weight().kind)
+                .next()
+                .unwrap_or(dependency_kind)
+        } else {
+            dependency_kind
+        };
+        let enabled_by = (graph.graph[dependent].name.to_string(), requested);
+
+        report.add(Violation {
+            parent: graph.graph[start].name.clone(),
+            dependency: package.name.clone(),
+            path,
+            dependency_kind,
+            kind: ViolationKind::ForbiddenFeature {
+                feature: feature.clone(),
+                enabled_by,
+            },
+            severity: rule.severity,
+            reason: rule.reason.clone(),
+            help: rule.help.clone(),
+            origin: rule.origin.clone(),
+            known: false,
+        });
+    }
+}
+
+/// Walks every package reachable from `start` through edges of the rule's
+/// kinds breadth-first, so that each forbidden package is reported with the
+/// shortest path leading to it.
+fn check_transitive(
+    graph: &Graph,
+    rule: &DependencyRule,
+    start: NodeIndex,
+    exemptions: &mut Exemptions<'_>,
+    report: &mut ViolationReport,
+) {
+    let paths = shortest_paths(graph, &[start], |edge| rule.applies_to(edge.weight()));
+    for &node in paths.reached() {
+        let package = &graph.graph[node];
+        let Some(matcher) = rule.forbidden_match(package) else {
+            continue;
+        };
+        if exemptions.exempts(&graph.graph[start], package) {
+            continue;
+        }
+
+        let (path, dependency_kind) = paths.path(node);
+        report.add(Violation {
+            parent: graph.graph[start].name.clone(),
+            dependency: package.name.clone(),
+            path,
+            dependency_kind,
+            kind: forbidden_violation(matcher, package),
+            severity: rule.severity,
+            reason: rule.reason.clone(),
+            help: rule.help.clone(),
+            origin: rule.origin.clone(),
+            known: false,
+        });
+    }
+}
+
+pub fn print_summary(writer: &mut impl Write, report: &ViolationReport) -> Result<(), Error> {
+    if !report.exception_warnings.is_empty() {
+        writeln!(writer)?;
+        for warning in &report.exception_warnings {
+            writeln!(writer, "warning: {warning}")?;
+        }
+    }
+
+    if !report.has_violations() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    let known = report.violations.iter().filter(|v| v.known).count();
+    if known > 0 {
+        writeln!(
+            writer,
+            "{} dependency rule violation(s) found ({known} in baseline):",
+            report.violations.len()
+        )?;
+    } else {
+        writeln!(
+            writer,
+            "{} dependency rule violation(s) found:",
+            report.violations.len()
+        )?;
+    }
+    for violation in &report.violations {
+        let known = if violation.known {
+            " (in baseline)"
+        } else {
+            ""
+        };
+        writeln!(writer, "  {}: {violation}{known}", violation.severity)?;
+        if let Some(reason) = &violation.reason {
+            writeln!(writer, "      reason: {reason}")?;
+        }
+        if let Some(help) = &violation.help {
+            writeln!(writer, "      help: {help}")?;
+        }
+        if let Some(origin) = &violation.origin {
+            writeln!(writer, "      defined in: {}", origin.display())?;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dependency_graph::{DependencyGraphBuildConfigs, build_dependency_graph};
-    use crate::dependency_rule::DependencyRules;
-    use crate::metadata::{CollectMetadataConfig, collect_metadata};
+    use crate::dependency_graph::tests::demo_graph;
+    use crate::dependency_rule::{Layer, PackageMatcher, SourceMatcher};
     use anyhow::Result;
 
     #[test]
     fn test_check_violations_no_violation() -> Result<()> {
-        let config = CollectMetadataConfig {
-            manifest_path: Some("tests/demo_crates/clean-arch/Cargo.toml".to_string()),
-            ..CollectMetadataConfig::default()
-        };
-        let metadata = collect_metadata(config)?;
-        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
+        let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
         let rules =
             DependencyRules::from_file("tests/demo_crates/clean-arch/dependency_rules.toml")?;
 
@@ -81,12 +1077,7 @@ mod tests {
 
     #[test]
     fn test_check_violations_with_violation() -> Result<()> {
-        let config = CollectMetadataConfig {
-            manifest_path: Some("tests/demo_crates/tangled-clean-arch/Cargo.toml".to_string()),
-            ..CollectMetadataConfig::default()
-        };
-        let metadata = collect_metadata(config)?;
-        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
         let rules = DependencyRules::from_file(
             "tests/demo_crates/tangled-clean-arch/dependency_rules.toml",
         )?;
@@ -100,12 +1091,7 @@ mod tests {
 
     #[test]
     fn test_check_violations_is_violation_lookup() -> Result<()> {
-        let config = CollectMetadataConfig {
-            manifest_path: Some("tests/demo_crates/tangled-clean-arch/Cargo.toml".to_string()),
-            ..CollectMetadataConfig::default()
-        };
-        let metadata = collect_metadata(config)?;
-        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
         let rules = DependencyRules::from_file(
             "tests/demo_crates/tangled-clean-arch/dependency_rules.toml",
         )?;
@@ -134,13 +1120,8 @@ mod tests {
 
     #[test]
     fn test_check_violations_with_empty_rules() -> Result<()> {
-        let config = CollectMetadataConfig {
-            manifest_path: Some("tests/demo_crates/tangled-clean-arch/Cargo.toml".to_string()),
-            ..CollectMetadataConfig::default()
-        };
-        let metadata = collect_metadata(config)?;
-        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
-        let rules = DependencyRules { rules: Vec::new() };
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::default();
 
         let report = check_violations(&graph, &rules);
 
@@ -151,19 +1132,15 @@ mod tests {
 
     #[test]
     fn test_check_violations_rule_package_not_in_graph() -> Result<()> {
-        let config = CollectMetadataConfig {
-            manifest_path: Some("tests/demo_crates/clean-arch/Cargo.toml".to_string()),
-            ..CollectMetadataConfig::default()
-        };
-        let metadata = collect_metadata(config)?;
-        let graph = build_dependency_graph(&metadata, DependencyGraphBuildConfigs::default())?;
+        let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
 
         // グラフに存在しないパッケージ名のルール
         let rules = DependencyRules {
-            rules: vec![crate::dependency_rule::DependencyRule::new(
-                "nonexistent-package".to_string(),
-                HashSet::from(["also-nonexistent".to_string()]),
+            rules: vec![DependencyRule::new(
+                PackageMatcher::Exact("nonexistent-package".to_string()),
+                vec![PackageMatcher::Exact("also-nonexistent".to_string()).into()],
             )],
+            ..DependencyRules::default()
         };
 
         let report = check_violations(&graph, &rules);
@@ -171,4 +1148,888 @@ mod tests {
         assert!(!report.has_violations());
         Ok(())
     }
+
+    #[test]
+    fn test_check_violations_glob_patterns() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule::new(
+                PackageMatcher::new("tangled-ca-*")?,
+                vec![PackageMatcher::new("*-handler")?.into()],
+            )],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert!(report.is_violation("tangled-ca-core", "tangled-ca-handler"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-handler"));
+        assert!(!report.is_violation("tangled-ca-core", "tangled-ca-database"));
+        assert_eq!(report.violations.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_regex_matchers() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule::new(
+                PackageMatcher::regex("^tangled-ca-(core|interactor)$")?,
+                vec![PackageMatcher::regex("(database|repositry)$")?.into()],
+            )],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert!(report.is_violation("tangled-ca-core", "tangled-ca-database"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-database"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-repositry"));
+        assert!(!report.is_violation("tangled-ca-database", "tangled-ca-repositry"));
+        assert_eq!(report.violations.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_allowed_dependencies() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                allowed_dependencies: Some(vec![PackageMatcher::new("tangled-ca-core")?.into()]),
+                ..DependencyRule::new(PackageMatcher::new("tangled-ca-interactor")?, Vec::new())
+            }],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert!(!report.is_violation("tangled-ca-interactor", "tangled-ca-core"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-database"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-handler"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-repositry"));
+        assert_eq!(report.violations.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_allowed_and_forbidden_dependencies() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                allowed_dependencies: Some(vec![PackageMatcher::new("tangled-ca-*")?.into()]),
+                ..DependencyRule::new(
+                    PackageMatcher::new("tangled-ca-interactor")?,
+                    vec![PackageMatcher::new("tangled-ca-handler")?.into()],
+                )
+            }],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-handler"));
+        assert_eq!(report.violations.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_layers() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/layered_rules_test.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        assert!(report.is_violation("tangled-ca-core", "tangled-ca-database"));
+        assert!(report.is_violation("tangled-ca-core", "tangled-ca-handler"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-database"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-handler"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-repositry"));
+        assert!(!report.is_violation("tangled-ca-interactor", "tangled-ca-core"));
+        assert!(!report.is_violation("tangled-ca-database", "tangled-ca-repositry"));
+        assert_eq!(report.violations.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_layers_with_milder_rule() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let mut rules = DependencyRules::from_file("tests/test_files/layered_rules_test.toml")?;
+        rules.rules.push(DependencyRule {
+            severity: Severity::Warning,
+            ..DependencyRule::new(
+                PackageMatcher::new("tangled-ca-core")?,
+                vec![PackageMatcher::new("tangled-ca-database")?.into()],
+            )
+        });
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(
+            report.violation_severity(
+                "tangled-ca-core",
+                "tangled-ca-database",
+                DependencyKind::Normal
+            ),
+            Some(Severity::Error)
+        );
+        assert_eq!(report.max_severity(), Some(Severity::Error));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_strict_layers() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let layer = |name: &str, package: &str, strict: bool| -> Result<Layer> {
+            Ok(Layer::new(
+                name.to_string(),
+                vec![PackageMatcher::new(package)?],
+                strict,
+            ))
+        };
+        let rules = DependencyRules {
+            layers: vec![
+                layer("use-case", "tangled-ca-interactor", true)?,
+                layer("adapter", "tangled-ca-repositry", false)?,
+                layer("domain", "tangled-ca-core", false)?,
+            ],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        // interactor skips the adapter layer when depending on core
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-core"));
+        assert!(!report.is_violation("tangled-ca-interactor", "tangled-ca-repositry"));
+        assert_eq!(report.violations.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_transitive() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
+
+        let rule = DependencyRule::new(
+            PackageMatcher::new("ca-database")?,
+            vec![PackageMatcher::new("ca-core")?.into()],
+        );
+        let direct_rules = DependencyRules {
+            rules: vec![rule.clone()],
+            ..DependencyRules::default()
+        };
+        assert!(!check_violations(&graph, &direct_rules).has_violations());
+
+        let transitive_rules = DependencyRules {
+            rules: vec![DependencyRule {
+                transitive: true,
+                ..rule
+            }],
+            ..DependencyRules::default()
+        };
+        let report = check_violations(&graph, &transitive_rules);
+
+        assert_eq!(
+            report.violations,
+            vec![Violation {
+                parent: "ca-database".to_string(),
+                dependency: "ca-core".to_string(),
+                path: vec![
+                    "ca-database".to_string(),
+                    "ca-repositry".to_string(),
+                    "ca-interactor".to_string(),
+                    "ca-core".to_string(),
+                ],
+                dependency_kind: DependencyKind::Normal,
+                kind: ViolationKind::Forbidden,
+                severity: Severity::Error,
+                reason: None,
+                help: None,
+                origin: None,
+                known: false,
+            }]
+        );
+        // the first edge of the path is the one highlighted in the tree
+        assert!(report.is_violation("ca-database", "ca-repositry"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_transitive_order() -> Result<()> {
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                transitive: true,
+                ..DependencyRule::new(
+                    PackageMatcher::new("*")?,
+                    vec![PackageMatcher::new("libc")?.into()],
+                )
+            }],
+            ..DependencyRules::default()
+        };
+        // every graph hashes its packages differently
+        let messages = || -> Result<Vec<String>> {
+            let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
+            let report = check_violations(&graph, &rules);
+            Ok(report.violations.iter().map(|v| v.to_string()).collect())
+        };
+
+        let first = messages()?;
+        assert!(first.len() > 1);
+        for _ in 0..3 {
+            assert_eq!(messages()?, first);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_transitive_keeps_direct_path() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                transitive: true,
+                ..DependencyRule::new(
+                    PackageMatcher::new("tangled-ca-interactor")?,
+                    vec![PackageMatcher::new("tangled-ca-repositry")?.into()],
+                )
+            }],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            report.violations[0].path,
+            vec!["tangled-ca-interactor", "tangled-ca-repositry"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_summary() {
+        let mut report = ViolationReport::default();
+        report.add(Violation {
+            parent: "ca-core".to_string(),
+            dependency: "ca-handler".to_string(),
+            path: vec![
+                "ca-core".to_string(),
+                "ca-database".to_string(),
+                "ca-handler".to_string(),
+            ],
+            dependency_kind: DependencyKind::Normal,
+            kind: ViolationKind::Forbidden,
+            severity: Severity::Error,
+            reason: None,
+            help: None,
+            origin: None,
+            known: false,
+        });
+        report.add(Violation {
+            parent: "ca-core".to_string(),
+            dependency: "ca-handler".to_string(),
+            path: vec!["ca-core".to_string(), "ca-handler".to_string()],
+            dependency_kind: DependencyKind::Normal,
+            kind: ViolationKind::Layer {
+                from: "domain".to_string(),
+                to: "handler".to_string(),
+            },
+            severity: Severity::Error,
+            reason: None,
+            help: None,
+            origin: None,
+            known: false,
+        });
+
+        let mut buf = Vec::new();
+        print_summary(&mut buf, &report).unwrap();
+
+        let output = String::from_utf8(buf).unwrap();
+        assert!(output.contains("2 dependency rule violation(s) found:"));
+        assert!(
+            output.contains("  error: ca-core -> ca-database -> ca-handler: forbidden dependency")
+        );
+        assert!(output.contains(
+            "  error: ca-core -> ca-handler: layer 'domain' must not depend on higher layer 'handler'"
+        ));
+    }
+
+    #[test]
+    fn test_report_keeps_each_kind_and_edge() {
+        let violation = |dependency_kind, kind| Violation {
+            parent: "ca-core".to_string(),
+            dependency: "ca-handler".to_string(),
+            path: vec!["ca-core".to_string(), "ca-handler".to_string()],
+            dependency_kind,
+            kind,
+            severity: Severity::Error,
+            reason: None,
+            help: None,
+            origin: None,
+            known: false,
+        };
+        let mut report = ViolationReport::default();
+        report.add(violation(DependencyKind::Normal, ViolationKind::Forbidden));
+        report.add(violation(
+            DependencyKind::Development,
+            ViolationKind::Forbidden,
+        ));
+        report.add(violation(DependencyKind::Normal, ViolationKind::NotAllowed));
+        report.add(violation(DependencyKind::Normal, ViolationKind::Forbidden));
+
+        assert_eq!(report.violations.len(), 3);
+        assert!(report.is_violation_of_kind("ca-core", "ca-handler", DependencyKind::Normal));
+        assert!(report.is_violation_of_kind("ca-core", "ca-handler", DependencyKind::Development));
+    }
+
+    #[test]
+    fn test_report_moves_edge_of_replaced_violation() {
+        let violation = |via: &str, severity| Violation {
+            parent: "ca-handler".to_string(),
+            dependency: "ca-database".to_string(),
+            path: vec![
+                "ca-handler".to_string(),
+                via.to_string(),
+                "ca-database".to_string(),
+            ],
+            dependency_kind: DependencyKind::Normal,
+            kind: ViolationKind::Forbidden,
+            severity,
+            reason: None,
+            help: None,
+            origin: None,
+            known: false,
+        };
+        let mut report = ViolationReport::default();
+        report.add(violation("ca-interactor", Severity::Warning));
+        report.add(violation("ca-repositry", Severity::Error));
+
+        assert_eq!(report.violations.len(), 1);
+        assert!(!report.is_violation("ca-handler", "ca-interactor"));
+        assert_eq!(
+            report.violation_severity("ca-handler", "ca-repositry", DependencyKind::Normal),
+            Some(Severity::Error)
+        );
+    }
+
+    #[test]
+    fn test_check_violations_keeps_most_severe() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rule = |severity| -> Result<DependencyRule> {
+            Ok(DependencyRule {
+                severity,
+                ..DependencyRule::new(
+                    PackageMatcher::new("tangled-ca-interactor")?,
+                    vec![PackageMatcher::new("tangled-ca-repositry")?.into()],
+                )
+            })
+        };
+        let rules = DependencyRules {
+            rules: vec![rule(Severity::Info)?, rule(Severity::Warning)?],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.max_severity(), Some(Severity::Warning));
+        assert_eq!(
+            report.violation_severity(
+                "tangled-ca-interactor",
+                "tangled-ca-repositry",
+                DependencyKind::Normal
+            ),
+            Some(Severity::Warning)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_summary_without_violations() {
+        let mut buf = Vec::new();
+        print_summary(&mut buf, &ViolationReport::default()).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_check_violations_rule_kinds() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/kinds-clean-arch/Cargo.toml")?;
+        let rules =
+            DependencyRules::from_file("tests/demo_crates/kinds-clean-arch/dependency_rules.toml")?;
+
+        // interactor only uses database and codegen as dev/build dependencies
+        let report = check_violations(&graph, &rules);
+        assert!(!report.has_violations());
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                kinds: vec![DependencyKind::Development],
+                ..DependencyRule::new(
+                    PackageMatcher::new("kinds-interactor")?,
+                    vec![PackageMatcher::new("kinds-*")?.into()],
+                )
+            }],
+            ..DependencyRules::default()
+        };
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            report.violations[0].dependency_kind,
+            DependencyKind::Development
+        );
+        assert!(report.is_violation_of_kind(
+            "kinds-interactor",
+            "kinds-database",
+            DependencyKind::Development
+        ));
+        assert!(!report.is_violation_of_kind(
+            "kinds-interactor",
+            "kinds-database",
+            DependencyKind::Normal
+        ));
+        assert!(!report.is_violation("kinds-interactor", "kinds-codegen"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_forbidden_version_range() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let forbid = |version: &str| -> Result<DependencyRules> {
+            Ok(DependencyRules {
+                rules: vec![DependencyRule::new(
+                    PackageMatcher::new("tangled-ca-*")?,
+                    vec![DependencyMatcher::new(
+                        PackageMatcher::new("tangled-ca-handler")?,
+                        Some(VersionReq::parse(version)?),
+                    )],
+                )],
+                ..DependencyRules::default()
+            })
+        };
+
+        let report = check_violations(&graph, &forbid(">=0.2")?);
+        assert!(!report.has_violations());
+
+        let report = check_violations(&graph, &forbid("<0.2")?);
+        assert_eq!(report.violations.len(), 2);
+        assert_eq!(
+            report.violations[0].kind,
+            ViolationKind::ForbiddenVersion {
+                requirement: VersionReq::parse("<0.2")?,
+                resolved: Version::parse("0.1.0")?,
+            }
+        );
+        assert!(
+            report.violations[0]
+                .to_string()
+                .ends_with("resolved version 0.1.0 is in forbidden range '<0.2'")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_required_version_range() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                required_versions: vec![DependencyMatcher::new(
+                    PackageMatcher::new("tangled-ca-repositry")?,
+                    Some(VersionReq::parse(">=1.0")?),
+                )],
+                ..DependencyRule::new(PackageMatcher::new("tangled-ca-*")?, Vec::new())
+            }],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert!(report.is_violation("tangled-ca-database", "tangled-ca-repositry"));
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-repositry"));
+        assert_eq!(report.violations.len(), 2);
+        assert!(
+            report.violations[0]
+                .to_string()
+                .ends_with("resolved version 0.1.0 does not satisfy required range '>=1.0'")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_allowed_version_range() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                allowed_dependencies: Some(vec![DependencyMatcher::new(
+                    PackageMatcher::new("tangled-ca-repositry")?,
+                    Some(VersionReq::parse("^0.2")?),
+                )]),
+                ..DependencyRule::new(PackageMatcher::new("tangled-ca-database")?, Vec::new())
+            }],
+            ..DependencyRules::default()
+        };
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            report.violations[0].kind,
+            ViolationKind::RequiredVersion {
+                requirement: VersionReq::parse("^0.2")?,
+                resolved: Version::parse("0.1.0")?,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_sources() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/sources/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/demo_crates/sources/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        // workspace members are never treated as path dependencies
+        assert!(!report.is_violation("sources-release", "sources-core"));
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            report.violations[0].kind,
+            ViolationKind::ForbiddenSource {
+                source: "path".to_string()
+            }
+        );
+        assert!(report.is_violation("sources-release", "sources-vendored"));
+
+        let rules = DependencyRules {
+            rules: vec![DependencyRule {
+                sources: Some(vec![SourceMatcher::Registry(None)]),
+                ..DependencyRule::new(PackageMatcher::new("sources-*")?, Vec::new())
+            }],
+            ..DependencyRules::default()
+        };
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            report.violations[0].to_string(),
+            "sources-release -> sources-vendored: dependency source 'path' is not allowed"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_summary_with_reason_and_help() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/reason_rules_test.toml")?;
+
+        let report = check_violations(&graph, &rules);
+        assert!(report.violations.iter().all(|v| v.reason.is_some()));
+
+        let mut buf = Vec::new();
+        print_summary(&mut buf, &report)?;
+
+        let output = String::from_utf8(buf)?;
+        assert!(
+            output.contains(
+                "      reason: The domain core must not know about persistence or delivery"
+            )
+        );
+        assert!(output.contains(
+            "      help: Define a port trait in tangled-ca-core and implement it in the outer crate"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_reports_rule_origin() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/compose/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        let origin_of = |parent: &str, dependency: &str| {
+            report
+                .violations
+                .iter()
+                .find(|v| v.parent == parent && v.dependency == dependency)
+                .and_then(|v| v.origin.clone())
+        };
+        assert_eq!(
+            origin_of("tangled-ca-core", "tangled-ca-handler"),
+            Some(PathBuf::from(
+                "tests/test_files/compose/../shared/deprule-base.toml"
+            ))
+        );
+        assert_eq!(
+            origin_of("tangled-ca-interactor", "tangled-ca-repositry"),
+            Some(PathBuf::from(
+                "tests/test_files/compose/rules.d/interactor.toml"
+            ))
+        );
+
+        // layers and budgets keep the file they were read from too
+        let origin_of_kind = |parent: &str, kind: &str| {
+            report
+                .violations
+                .iter()
+                .find(|v| v.parent == parent && v.kind.id() == kind)
+                .and_then(|v| v.origin.clone())
+        };
+        assert_eq!(
+            origin_of_kind("tangled-ca-core", "layer"),
+            Some(PathBuf::from(
+                "tests/test_files/compose/dependency_rules.toml"
+            ))
+        );
+        assert_eq!(
+            origin_of_kind("tangled-ca-interactor", "direct-budget"),
+            Some(PathBuf::from(
+                "tests/test_files/compose/rules.d/budgets.toml"
+            ))
+        );
+
+        let mut buf = Vec::new();
+        print_summary(&mut buf, &report)?;
+        let output = String::from_utf8(buf)?;
+        assert!(
+            output.contains("      defined in: tests/test_files/compose/rules.d/interactor.toml")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_label_selectors() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/labels/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/demo_crates/labels/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert!(report.is_violation("labels-billing", "labels-postgres"));
+        assert!(!report.is_violation("labels-app", "labels-postgres"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_exceptions_until_expiry() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/exceptions_rules_test.toml")?;
+        let date = |text: &str| NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap();
+
+        let report = check_violations_at(&graph, &rules, date("2026-06-30"));
+        assert_eq!(report.violations.len(), 1);
+        assert!(report.is_violation("tangled-ca-core", "tangled-ca-database"));
+        assert_eq!(
+            report.exception_warnings,
+            vec![
+                "exception for tangled-ca-handler -> tangled-ca-core does not match any violation and can be removed"
+            ]
+        );
+
+        // the interactor exception expired the day before
+        let report = check_violations_at(&graph, &rules, date("2026-07-01"));
+        assert_eq!(report.violations.len(), 4);
+        assert!(!report.is_violation("tangled-ca-core", "tangled-ca-handler"));
+        assert!(report.exception_warnings.contains(
+            &"exception for tangled-ca-interactor -> tangled-ca-* expired on 2026-06-30: Interactor is being split up".to_string()
+        ));
+
+        let report = check_violations_at(&graph, &rules, date("2027-01-01"));
+        assert_eq!(report.violations.len(), 5);
+        assert!(report.is_violation("tangled-ca-core", "tangled-ca-handler"));
+
+        let mut buf = Vec::new();
+        print_summary(&mut buf, &report)?;
+        let output = String::from_utf8(buf)?;
+        assert!(output.contains(
+            "warning: exception for tangled-ca-core -> tangled-ca-handler (owner: core-team) expired on 2026-12-31"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_no_cycles() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/cycles/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/demo_crates/cycles/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        let messages: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "cycles-core -> cycles-mocks -> cycles-core (dev-dependency): dependency cycle",
+                "cycles-core -> cycles-test-utils -> cycles-core (dev-dependency): dependency cycle",
+            ]
+        );
+        assert!(report.is_violation_of_kind(
+            "cycles-core",
+            "cycles-test-utils",
+            DependencyKind::Development
+        ));
+
+        let report = check_violations(&graph, &DependencyRules::default());
+        assert!(!report.has_violations());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_budgets() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/budget_rules_test.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        let messages: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(messages.len(), 3, "{messages:?}");
+        assert!(
+            messages[0].starts_with("ca-handler: 3 direct dependencies exceed the budget of 2")
+        );
+        assert!(messages[0].contains("biggest contributors: actix-web ("));
+        assert!(messages[1].starts_with("ca-handler: "));
+        assert!(messages[1].contains("transitive dependencies exceed the budget of 10"));
+        assert_eq!(
+            messages[2],
+            "ca-repositry: 2 transitive dependencies exceed the budget of 1; \
+             biggest contributors: ca-interactor (2)"
+        );
+        assert_eq!(report.violations[2].severity, Severity::Warning);
+        assert!(!report.is_violation("ca-handler", "ca-handler"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_facades() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/tangled-clean-arch/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/facade_rules_test.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        let messages: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "tangled-ca-interactor -> tangled-ca-repositry: \
+                 path bypasses facade 'tangled-ca-database'"
+            ]
+        );
+        assert_eq!(
+            report.violations[0].reason.as_deref(),
+            Some("repositories are only used through the database layer")
+        );
+        assert!(report.is_violation("tangled-ca-interactor", "tangled-ca-repositry"));
+        assert!(!report.is_violation("tangled-ca-core", "tangled-ca-repositry"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_no_duplicate_versions() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/duplicates/Cargo.toml")?;
+        let rules =
+            DependencyRules::from_file("tests/demo_crates/duplicates/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        let message = report.violations[0].to_string();
+        assert!(
+            message.starts_with("itoa: 2 incompatible versions: 0.4."),
+            "{message}"
+        );
+        assert!(message.contains(" (via dup-app), 1."));
+        assert!(message.ends_with(" (via dup-app, dup-lib)"));
+        assert!(!report.is_violation("itoa", "itoa"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_licenses() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/duplicates/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/test_files/license_rules_test.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        let messages: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "dup-app -> itoa: license 'MIT OR Apache-2.0' is not allowed",
+                "dup-lib -> itoa: license 'MIT OR Apache-2.0' is not allowed",
+            ]
+        );
+        assert!(report.is_violation("dup-app", "itoa"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_rule_platforms() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/platforms/Cargo.toml")?;
+        let rules =
+            DependencyRules::from_file("tests/demo_crates/platforms/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        assert!(report.is_violation("plat-app", "plat-web"));
+        assert!(!report.is_violation("plat-app", "plat-native"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_forbidden_features() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/features/Cargo.toml")?;
+        let rules = DependencyRules::from_file("tests/demo_crates/features/dependency_rules.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        let messages: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "feat-core -> feat-util -> feat-lib: feature 'std' is forbidden, \
+                 enabled by feat-util through feature 'full'"
+            ]
+        );
+        assert!(report.is_violation("feat-core", "feat-util"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_violations_no_default_features() -> Result<()> {
+        let graph = demo_graph("tests/demo_crates/features/Cargo.toml")?;
+        let rules =
+            DependencyRules::from_file("tests/test_files/default_features_rules_test.toml")?;
+
+        let report = check_violations(&graph, &rules);
+
+        assert_eq!(report.violations.len(), 1);
+        let violation = &report.violations[0];
+        assert_eq!(
+            (violation.parent.as_str(), violation.dependency.as_str()),
+            ("feat-app", "feat-lib")
+        );
+        let ViolationKind::DefaultFeaturesEnabled { manifest, entry } = &violation.kind else {
+            panic!("unexpected violation: {violation}");
+        };
+        assert!(manifest.ends_with("features/app/Cargo.toml"));
+        assert_eq!(entry, "`feat-lib` in [dependencies]");
+        assert!(
+            violation
+                .to_string()
+                .starts_with("feat-app -> feat-lib: `feat-lib` in [dependencies] of '")
+        );
+        assert!(
+            violation
+                .to_string()
+                .ends_with("' must set default-features = false")
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,215 @@
+use super::{PackageMatcher, Severity};
+use anyhow::{Error, anyhow};
+use cargo_metadata::Package;
+use spdx::{Expression, LicenseId, LicenseReq, ParseMode};
+use std::path::PathBuf;
+
+/// The `[licenses]` policy, checked against every package reachable from the
+/// workspace members.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LicensePolicy {
+    /// When not empty, a license expression must be satisfiable with these.
+    pub(crate) allow: Vec<LicenseId>,
+    pub(crate) deny: Vec<LicenseId>,
+    pub(crate) exceptions: Vec<LicenseException>,
+    pub(crate) severity: Severity,
+    pub(crate) reason: Option<String>,
+    /// Rules file the policy was read from.
+    pub(crate) origin: Option<PathBuf>,
+}
+
+/// Additional licenses accepted for some crates. An exception without
+/// licenses skips the crate altogether.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LicenseException {
+    pub(crate) package: PackageMatcher,
+    pub(crate) allow: Vec<LicenseId>,
+}
+
+/// Why a package does not meet the license policy.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LicenseProblem {
+    /// The package declares no `license` expression.
+    Missing,
+    Invalid {
+        expression: String,
+    },
+    /// Every way to satisfy the expression involves a denied license.
+    Denied {
+        expression: String,
+        license: String,
+    },
+    /// The expression cannot be satisfied with the allowed licenses.
+    NotAllowed {
+        expression: String,
+    },
+}
+
+/// Resolves an SPDX license identifier such as `Apache-2.0`. GNU licenses
+/// resolve to their root license, `GPL-3.0-only` to `GPL-3.0`, the same way
+/// they are read from license expressions.
+pub(crate) fn license_id(name: &str) -> Result<LicenseId, Error> {
+    spdx::license_id(name)
+        .and_then(|id| LicenseReq::from(id).license.id())
+        .ok_or_else(|| anyhow!("unknown SPDX license '{name}'"))
+}
+
+impl LicensePolicy {
+    pub(crate) fn check(&self, package: &Package) -> Option<LicenseProblem> {
+        let exceptions: Vec<&LicenseException> = self
+            .exceptions
+            .iter()
+            .filter(|exception| exception.package.matches(package))
+            .collect();
+        if exceptions
+            .iter()
+            .any(|exception| exception.allow.is_empty())
+        {
+            return None;
+        }
+
+        let Some(text) = &package.license else {
+            return (!self.allow.is_empty()).then_some(LicenseProblem::Missing);
+        };
+        let Ok(expression) = Expression::parse_mode(text, ParseMode::LAX) else {
+            return Some(LicenseProblem::Invalid {
+                expression: text.clone(),
+            });
+        };
+
+        let excepted = |req: &LicenseReq| {
+            req.license.id().is_some_and(|id| {
+                exceptions
+                    .iter()
+                    .any(|exception| exception.allow.contains(&id))
+            })
+        };
+        let denied = |req: &LicenseReq| req.license.id().is_some_and(|id| self.deny.contains(&id));
+        let allowed = |req: &LicenseReq| {
+            self.allow.is_empty() || req.license.id().is_some_and(|id| self.allow.contains(&id))
+        };
+
+        if expression.evaluate(|req| excepted(req) || (!denied(req) && allowed(req))) {
+            return None;
+        }
+        match expression.evaluate_with_failures(|req| excepted(req) || !denied(req)) {
+            Err(failures) => Some(LicenseProblem::Denied {
+                expression: text.clone(),
+                license: failures
+                    .first()
+                    .map(|failure| failure.req.to_string())
+                    .unwrap_or_default(),
+            }),
+            Ok(()) => Some(LicenseProblem::NotAllowed {
+                expression: text.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_rule::matcher::tests::package;
+
+    fn licensed(name: &str, license: Option<&str>) -> Package {
+        let mut package = package(name);
+        package.license = license.map(str::to_string);
+        package
+    }
+
+    fn policy(allow: &[&str], deny: &[&str]) -> LicensePolicy {
+        let ids = |names: &[&str]| names.iter().map(|name| license_id(name).unwrap()).collect();
+        LicensePolicy {
+            allow: ids(allow),
+            deny: ids(deny),
+            exceptions: Vec::new(),
+            severity: Severity::Error,
+            reason: None,
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn test_check_allowed_licenses() {
+        let policy = policy(&["MIT", "Apache-2.0"], &[]);
+
+        assert_eq!(
+            policy.check(&licensed("dual", Some("MIT OR Apache-2.0"))),
+            None
+        );
+        assert_eq!(
+            policy.check(&licensed("slash", Some("MIT/Apache-2.0"))),
+            None
+        );
+        assert_eq!(
+            policy.check(&licensed("gpl", Some("GPL-3.0-only OR MIT"))),
+            None
+        );
+        assert_eq!(
+            policy.check(&licensed("both", Some("MIT AND ISC"))),
+            Some(LicenseProblem::NotAllowed {
+                expression: "MIT AND ISC".to_string()
+            })
+        );
+        assert_eq!(
+            policy.check(&licensed("none", None)),
+            Some(LicenseProblem::Missing)
+        );
+        assert_eq!(
+            policy.check(&licensed("invalid", Some("MIT OR OR"))),
+            Some(LicenseProblem::Invalid {
+                expression: "MIT OR OR".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_denied_licenses() {
+        let policy = policy(&[], &["GPL-3.0-only"]);
+
+        assert_eq!(
+            policy.check(&licensed("gpl", Some("GPL-3.0-only"))),
+            Some(LicenseProblem::Denied {
+                expression: "GPL-3.0-only".to_string(),
+                license: "GPL-3.0".to_string(),
+            })
+        );
+        assert_eq!(
+            policy.check(&licensed("dual", Some("GPL-3.0-only OR MIT"))),
+            None
+        );
+        assert!(
+            policy
+                .check(&licensed("later", Some("GPL-3.0-or-later")))
+                .is_some()
+        );
+        assert_eq!(policy.check(&licensed("none", None)), None);
+    }
+
+    #[test]
+    fn test_check_license_exceptions() {
+        let mut policy = policy(&["MIT"], &["OpenSSL"]);
+        policy.exceptions = vec![
+            LicenseException {
+                package: PackageMatcher::Exact("ring".to_string()),
+                allow: vec![license_id("OpenSSL").unwrap(), license_id("ISC").unwrap()],
+            },
+            LicenseException {
+                package: PackageMatcher::Exact("internal".to_string()),
+                allow: Vec::new(),
+            },
+        ];
+
+        assert_eq!(
+            policy.check(&licensed("ring", Some("MIT AND ISC AND OpenSSL"))),
+            None
+        );
+        assert_eq!(policy.check(&licensed("internal", None)), None);
+        assert!(
+            policy
+                .check(&licensed("other", Some("MIT AND ISC")))
+                .is_some()
+        );
+    }
+}
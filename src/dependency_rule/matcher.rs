@@ -0,0 +1,288 @@
+use anyhow::{Error, anyhow};
+use cargo_metadata::Package;
+use regex::Regex;
+use semver::VersionReq;
+use std::fmt;
+
+/// A compiled package selector used on either side of a dependency rule.
+///
+/// Plain names are compared exactly, while names containing glob
+/// metacharacters (`*`, `?`, `[`, `]`) are compiled into a glob pattern.
+/// Regular expressions are only used when requested explicitly.
+/// Labels match packages tagged in their own manifest, see [`package_labels`].
+/// A group matches any of its members.
+#[derive(Debug, Clone)]
+pub(crate) enum PackageMatcher {
+    Exact(String),
+    Glob(glob::Pattern),
+    Regex(Regex),
+    Label(String),
+    Group {
+        name: String,
+        members: Vec<PackageMatcher>,
+    },
+}
+
+impl PackageMatcher {
+    pub(crate) fn new(pattern: &str) -> Result<Self, Error> {
+        if !pattern.contains(['*', '?', '[', ']']) {
+            return Ok(Self::Exact(pattern.to_string()));
+        }
+
+        glob::Pattern::new(pattern)
+            .map(Self::Glob)
+            .map_err(|err| anyhow!("invalid glob pattern '{pattern}': {err}"))
+    }
+
+    pub(crate) fn regex(pattern: &str) -> Result<Self, Error> {
+        Regex::new(pattern)
+            .map(Self::Regex)
+            .map_err(|err| anyhow!("invalid regex '{pattern}': {err}"))
+    }
+
+    pub(crate) fn matches(&self, package: &Package) -> bool {
+        match self {
+            Self::Exact(name) => *name == package.name,
+            Self::Glob(pattern) => pattern.matches(&package.name),
+            Self::Regex(regex) => regex.is_match(&package.name),
+            Self::Label(label) => package_labels(package).any(|l| l == label),
+            Self::Group { members, .. } => members.iter().any(|member| member.matches(package)),
+        }
+    }
+}
+
+/// Labels of a package: the `layer` and `tags` entries of its
+/// `[package.metadata.deprule]` table.
+fn package_labels(package: &Package) -> impl Iterator<Item = &str> {
+    let deprule = package.metadata.get("deprule");
+    let layer = deprule
+        .and_then(|deprule| deprule.get("layer"))
+        .and_then(|layer| layer.as_str());
+    let tags = deprule
+        .and_then(|deprule| deprule.get("tags"))
+        .and_then(|tags| tags.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag.as_str());
+
+    layer.into_iter().chain(tags)
+}
+
+impl PartialEq for PackageMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Exact(a), Self::Exact(b)) => a == b,
+            (Self::Glob(a), Self::Glob(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            (Self::Label(a), Self::Label(b)) => a == b,
+            (
+                Self::Group {
+                    name: a,
+                    members: a_members,
+                },
+                Self::Group {
+                    name: b,
+                    members: b_members,
+                },
+            ) => a == b && a_members == b_members,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for PackageMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(name) => f.write_str(name),
+            Self::Glob(pattern) => f.write_str(pattern.as_str()),
+            Self::Regex(regex) => write!(f, "/{}/", regex.as_str()),
+            Self::Label(label) => write!(f, "label:{label}"),
+            Self::Group { name, .. } => write!(f, "@{name}"),
+        }
+    }
+}
+
+/// A package selector optionally narrowed to a semver range, evaluated against
+/// the resolved version of the dependency.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DependencyMatcher {
+    pub(crate) package: PackageMatcher,
+    pub(crate) version: Option<VersionReq>,
+}
+
+impl DependencyMatcher {
+    pub(crate) fn new(package: PackageMatcher, version: Option<VersionReq>) -> Self {
+        Self { package, version }
+    }
+
+    pub(crate) fn matches(&self, package: &Package) -> bool {
+        self.package.matches(package) && self.matches_version(package)
+    }
+
+    pub(crate) fn matches_version(&self, package: &Package) -> bool {
+        self.version
+            .as_ref()
+            .is_none_or(|version| version.matches(&package.version))
+    }
+}
+
+impl From<PackageMatcher> for DependencyMatcher {
+    fn from(package: PackageMatcher) -> Self {
+        Self::new(package, None)
+    }
+}
+
+impl fmt::Display for DependencyMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} {}", self.package, version),
+            None => write!(f, "{}", self.package),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use cargo_metadata::{PackageBuilder, PackageId};
+    use semver::Version;
+
+    pub(crate) fn package(name: &str) -> Package {
+        versioned_package(name, "0.1.0")
+    }
+
+    pub(crate) fn versioned_package(name: &str, version: &str) -> Package {
+        PackageBuilder::new(
+            name.to_string(),
+            Version::parse(version).unwrap(),
+            PackageId {
+                repr: format!("{name} {version}"),
+            },
+            format!("/{name}/Cargo.toml"),
+        )
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_exact_name_matches_only_itself() {
+        let matcher = PackageMatcher::new("ca-core").unwrap();
+        assert_eq!(matcher, PackageMatcher::Exact("ca-core".to_string()));
+        assert!(matcher.matches(&package("ca-core")));
+        assert!(!matcher.matches(&package("ca-core-extra")));
+    }
+
+    #[test]
+    fn test_glob_pattern_matches_names() {
+        let matcher = PackageMatcher::new("*-domain-*").unwrap();
+        assert!(matches!(matcher, PackageMatcher::Glob(_)));
+        assert!(matcher.matches(&package("billing-domain-invoice")));
+        assert!(!matcher.matches(&package("billing-adapter-invoice")));
+
+        let matcher = PackageMatcher::new("tokio*").unwrap();
+        assert!(matcher.matches(&package("tokio")));
+        assert!(matcher.matches(&package("tokio-util")));
+        assert!(!matcher.matches(&package("mio")));
+    }
+
+    #[test]
+    fn test_malformed_glob_pattern() {
+        let result = PackageMatcher::new("billing-[domain");
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("invalid glob pattern 'billing-[domain'")
+        );
+    }
+
+    #[test]
+    fn test_regex_matches_names() {
+        let matcher = PackageMatcher::regex("^(api|web)-.+-v[0-9]+$").unwrap();
+        assert!(matcher.matches(&package("api-billing-v2")));
+        assert!(matcher.matches(&package("web-invoice-v10")));
+        assert!(!matcher.matches(&package("api-billing")));
+        assert!(!matcher.matches(&package("cli-billing-v2")));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let result = PackageMatcher::regex("^(api|web");
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("invalid regex '^(api|web'")
+        );
+    }
+
+    #[test]
+    fn test_regex_equality_compares_pattern() {
+        assert_eq!(
+            PackageMatcher::regex("^api-.*$").unwrap(),
+            PackageMatcher::regex("^api-.*$").unwrap()
+        );
+        assert_ne!(
+            PackageMatcher::regex("api").unwrap(),
+            PackageMatcher::new("api").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_display_keeps_original_pattern() {
+        assert_eq!(
+            PackageMatcher::new("ca-core").unwrap().to_string(),
+            "ca-core"
+        );
+        assert_eq!(
+            PackageMatcher::new("*-adapter-*").unwrap().to_string(),
+            "*-adapter-*"
+        );
+        assert_eq!(
+            PackageMatcher::regex("^api-.*$").unwrap().to_string(),
+            "/^api-.*$/"
+        );
+    }
+
+    #[test]
+    fn test_dependency_matcher_version_range() {
+        let matcher = DependencyMatcher::new(
+            PackageMatcher::new("openssl").unwrap(),
+            Some(VersionReq::parse("<0.10.60").unwrap()),
+        );
+        assert!(matcher.matches(&versioned_package("openssl", "0.10.55")));
+        assert!(!matcher.matches(&versioned_package("openssl", "0.10.60")));
+        assert!(!matcher.matches(&versioned_package("openssl-sys", "0.9.0")));
+        assert_eq!(matcher.to_string(), "openssl <0.10.60");
+    }
+
+    #[test]
+    fn test_dependency_matcher_without_version_matches_any_version() {
+        let matcher = DependencyMatcher::from(PackageMatcher::new("tokio").unwrap());
+        assert!(matcher.matches(&versioned_package("tokio", "0.2.0")));
+        assert!(matcher.matches(&versioned_package("tokio", "1.44.0")));
+        assert_eq!(matcher.to_string(), "tokio");
+    }
+
+    #[test]
+    fn test_label_matches_layer_and_tags() {
+        let mut domain = package("billing-domain");
+        domain.metadata = serde_json::json!({
+            "deprule": { "layer": "domain", "tags": ["pure", "wasm-safe"] }
+        });
+        let plain = package("billing-adapter");
+
+        for label in ["domain", "pure", "wasm-safe"] {
+            let matcher = PackageMatcher::Label(label.to_string());
+            assert!(matcher.matches(&domain));
+            assert!(!matcher.matches(&plain));
+        }
+        assert!(!PackageMatcher::Label("infrastructure".to_string()).matches(&domain));
+        assert_eq!(
+            PackageMatcher::Label("domain".to_string()).to_string(),
+            "label:domain"
+        );
+    }
+}
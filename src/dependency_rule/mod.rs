@@ -1,40 +1,399 @@
-use anyhow::{Context, Error};
-use std::{collections::HashSet, fs};
+use crate::dependency_graph::Edge;
+use anyhow::{Context, Error, bail};
+use cargo_metadata::{Dependency, DependencyKind, Metadata, Package};
+use chrono::NaiveDate;
+use semver::VersionReq;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+mod license;
+mod matcher;
+mod platform;
 mod rules_parser;
+mod source;
 
-use rules_parser::RulesFileSchema;
+pub(crate) use license::{LicenseException, LicensePolicy, LicenseProblem};
+pub(crate) use matcher::{DependencyMatcher, PackageMatcher};
+pub(crate) use platform::PlatformMatcher;
+use rules_parser::{RulesFileLoader, RulesFileSchema};
+use serde::{Deserialize, Serialize};
+pub(crate) use source::{PackageSource, SourceMatcher};
+use std::fmt;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct DependencyRules {
     pub(crate) rules: Vec<DependencyRule>,
+    /// Architecture layers ordered from the top (outermost) to the bottom.
+    pub(crate) layers: Vec<Layer>,
+    pub(crate) exceptions: Vec<Exception>,
+    pub(crate) no_cycles: Option<NoCycles>,
+    pub(crate) budgets: Vec<Budget>,
+    pub(crate) facades: Vec<Facade>,
+    pub(crate) no_duplicate_versions: Option<NoDuplicateVersions>,
+    pub(crate) licenses: Option<LicensePolicy>,
+}
+
+/// How serious a violation is. Violations below the `--fail-on` level are
+/// reported but do not fail the check.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => f.write_str("info"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct DependencyRule {
-    pub(crate) package: String,
-    pub(crate) forbidden_dependencies: HashSet<String>,
+    pub(crate) package: PackageMatcher,
+    pub(crate) forbidden_dependencies: Vec<DependencyMatcher>,
+    /// When set, the package may only depend on crates matching one of these.
+    pub(crate) allowed_dependencies: Option<Vec<DependencyMatcher>>,
+    /// Dependencies matching these names must satisfy the version range.
+    pub(crate) required_versions: Vec<DependencyMatcher>,
+    /// Also forbid reaching `forbidden_dependencies` through other crates.
+    pub(crate) transitive: bool,
+    /// Features that must not be enabled on the given crates anywhere in the
+    /// dependencies of a matching workspace member, by crate name.
+    pub(crate) forbidden_features: BTreeMap<String, Vec<String>>,
+    /// Dependencies that must be declared with `default-features = false`.
+    pub(crate) no_default_features: Vec<PackageMatcher>,
+    /// Dependency kinds the rule applies to.
+    pub(crate) kinds: Vec<DependencyKind>,
+    /// When set, the rule only applies to dependencies active on one of these
+    /// platforms. Unconditional dependencies are active everywhere.
+    pub(crate) platforms: Option<Vec<PlatformMatcher>>,
+    /// When set, dependencies must come from one of these sources.
+    pub(crate) sources: Option<Vec<SourceMatcher>>,
+    pub(crate) forbidden_sources: Vec<SourceMatcher>,
+    pub(crate) severity: Severity,
+    /// Architectural intent behind the rule, shown with each violation.
+    pub(crate) reason: Option<String>,
+    /// Suggestion on how to fix a violation of the rule.
+    pub(crate) help: Option<String>,
+    /// Rules file the rule was read from.
+    pub(crate) origin: Option<PathBuf>,
 }
 impl DependencyRule {
-    pub(crate) fn new(package: String, forbidden_dependencies: HashSet<String>) -> Self {
+    pub(crate) fn new(
+        package: PackageMatcher,
+        forbidden_dependencies: Vec<DependencyMatcher>,
+    ) -> Self {
         Self {
             package,
             forbidden_dependencies,
+            allowed_dependencies: None,
+            required_versions: Vec::new(),
+            transitive: false,
+            forbidden_features: BTreeMap::new(),
+            no_default_features: Vec::new(),
+            kinds: vec![
+                DependencyKind::Normal,
+                DependencyKind::Build,
+                DependencyKind::Development,
+            ],
+            platforms: None,
+            sources: None,
+            forbidden_sources: Vec::new(),
+            severity: Severity::default(),
+            reason: None,
+            help: None,
+            origin: None,
+        }
+    }
+
+    pub(crate) fn applies_to(&self, edge: &Edge) -> bool {
+        self.kinds.contains(&edge.kind)
+            && (!edge.is_conditional()
+                || self.platforms.as_ref().is_none_or(|platforms| {
+                    platforms.iter().any(|matcher| {
+                        edge.platforms
+                            .iter()
+                            .any(|platform| matcher.enables(platform))
+                    })
+                }))
+    }
+
+    pub(crate) fn forbidden_match(&self, dependency: &Package) -> Option<&DependencyMatcher> {
+        self.forbidden_dependencies
+            .iter()
+            .find(|matcher| matcher.matches(dependency))
+    }
+
+    /// Returns the version range the dependency fails to satisfy, either from
+    /// `required_versions` or from an allow-list entry with the same name.
+    pub(crate) fn unmet_version_requirement(&self, dependency: &Package) -> Option<&VersionReq> {
+        let required = self.required_versions.iter();
+        let allowed = self
+            .allowed_dependencies
+            .iter()
+            .flatten()
+            .filter(|_| !self.is_allowed(dependency));
+
+        required
+            .chain(allowed)
+            .filter(|matcher| matcher.package.matches(dependency))
+            .find(|matcher| !matcher.matches_version(dependency))
+            .and_then(|matcher| matcher.version.as_ref())
+    }
+
+    pub(crate) fn is_allowed(&self, dependency: &Package) -> bool {
+        self.allowed_dependencies
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|matcher| matcher.matches(dependency)))
+    }
+
+    /// Returns the declaration of `dependency` in the manifest of `package`
+    /// that keeps default features enabled although the rule requires them
+    /// to be disabled.
+    pub(crate) fn default_features_declaration<'a>(
+        &self,
+        package: &'a Package,
+        dependency: &Package,
+        kind: DependencyKind,
+    ) -> Option<&'a Dependency> {
+        if !self
+            .no_default_features
+            .iter()
+            .any(|matcher| matcher.matches(dependency))
+        {
+            return None;
+        }
+        package.dependencies.iter().find(|declaration| {
+            declaration.kind == kind
+                && declaration.name == dependency.name.as_str()
+                && declaration.req.matches(&dependency.version)
+                && declaration.uses_default_features
+        })
+    }
+
+    pub(crate) fn is_forbidden_source(&self, source: &PackageSource<'_>) -> bool {
+        self.forbidden_sources
+            .iter()
+            .any(|matcher| matcher.matches(source))
+    }
+
+    pub(crate) fn is_allowed_source(&self, source: &PackageSource<'_>) -> bool {
+        *source == PackageSource::Workspace
+            || self
+                .sources
+                .as_ref()
+                .is_none_or(|sources| sources.iter().any(|matcher| matcher.matches(source)))
+    }
+}
+
+/// Forbids dependency cycles made of edges of the given kinds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NoCycles {
+    pub(crate) kinds: Vec<DependencyKind>,
+    pub(crate) severity: Severity,
+    pub(crate) reason: Option<String>,
+    /// Rules file the setting was read from.
+    pub(crate) origin: Option<PathBuf>,
+}
+
+/// Forbids several semver-incompatible versions of one crate in the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NoDuplicateVersions {
+    /// Crates that are never reported.
+    pub(crate) skip: Vec<PackageMatcher>,
+    /// Number of incompatible versions tolerated for a crate, by crate name.
+    pub(crate) allow: BTreeMap<String, usize>,
+    pub(crate) severity: Severity,
+    pub(crate) reason: Option<String>,
+    /// Rules file the setting was read from.
+    pub(crate) origin: Option<PathBuf>,
+}
+impl NoDuplicateVersions {
+    pub(crate) fn is_skipped(&self, package: &Package) -> bool {
+        self.skip.iter().any(|matcher| matcher.matches(package))
+    }
+
+    pub(crate) fn allowed_versions(&self, name: &str) -> usize {
+        self.allow.get(name).copied().unwrap_or(1)
+    }
+}
+
+/// Limits how many crates matching workspace members may depend on.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Budget {
+    pub(crate) package: PackageMatcher,
+    pub(crate) max_direct: Option<usize>,
+    pub(crate) max_transitive: Option<usize>,
+    /// Dependency kinds counted against the budget.
+    pub(crate) kinds: Vec<DependencyKind>,
+    pub(crate) severity: Severity,
+    pub(crate) reason: Option<String>,
+    /// Rules file the budget was read from.
+    pub(crate) origin: Option<PathBuf>,
+}
+
+/// Lets matching workspace members reach `target` only through one of the
+/// `only_via` packages.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Facade {
+    pub(crate) package: PackageMatcher,
+    pub(crate) target: PackageMatcher,
+    pub(crate) only_via: Vec<PackageMatcher>,
+    /// Dependency kinds followed when looking for paths to `target`.
+    pub(crate) kinds: Vec<DependencyKind>,
+    pub(crate) severity: Severity,
+    pub(crate) reason: Option<String>,
+    /// Rules file the facade was read from.
+    pub(crate) origin: Option<PathBuf>,
+}
+
+impl Facade {
+    pub(crate) fn is_facade(&self, package: &Package) -> bool {
+        self.only_via.iter().any(|facade| facade.matches(package))
+    }
+}
+
+/// A known violation that is accepted until it expires.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Exception {
+    pub(crate) package: PackageMatcher,
+    pub(crate) dependency: PackageMatcher,
+    pub(crate) reason: String,
+    pub(crate) owner: Option<String>,
+    /// Last day on which the exception applies.
+    pub(crate) expires: NaiveDate,
+}
+impl Exception {
+    pub(crate) fn matches(&self, package: &Package, dependency: &Package) -> bool {
+        self.package.matches(package) && self.dependency.matches(dependency)
+    }
+
+    pub(crate) fn is_expired(&self, today: NaiveDate) -> bool {
+        today > self.expires
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.package, self.dependency)?;
+        if let Some(owner) = &self.owner {
+            write!(f, " (owner: {owner})")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Layer {
+    pub(crate) name: String,
+    pub(crate) packages: Vec<PackageMatcher>,
+    /// A strict layer may only depend on itself and the layer directly below it.
+    pub(crate) strict: bool,
+    pub(crate) severity: Severity,
+    /// Rules file the layer was read from.
+    pub(crate) origin: Option<PathBuf>,
+}
+impl Layer {
+    pub(crate) fn new(name: String, packages: Vec<PackageMatcher>, strict: bool) -> Self {
+        Self {
+            name,
+            packages,
+            strict,
+            severity: Severity::default(),
+            origin: None,
         }
     }
 }
 
 impl DependencyRules {
+    /// Returns the index of the first layer the package belongs to.
+    pub(crate) fn layer_of(&self, package: &Package) -> Option<usize> {
+        self.layers.iter().position(|layer| {
+            layer
+                .packages
+                .iter()
+                .any(|matcher| matcher.matches(package))
+        })
+    }
+
+    /// Reads the rules of a workspace from the rules file,
+    /// `[workspace.metadata.deprule]` in the workspace manifest and
+    /// `[package.metadata.deprule]` in the manifests of workspace members.
+    ///
+    /// The sources are merged in that order, and a package may only have a
+    /// rule in one of them. The layers, `[no_cycles]`, `[licenses]` and
+    /// `[no_duplicate_versions]` may likewise only be defined in one of them. The rules file at `rules_path` may be missing
+    /// unless `rules_path_required` is set, as long as one of the manifests
+    /// defines rules.
+    #[tracing::instrument(skip_all, fields(path = ?rules_path))]
+    pub(crate) fn for_workspace(
+        metadata: &Metadata,
+        rules_path: &Path,
+        rules_path_required: bool,
+    ) -> Result<DependencyRules, Error> {
+        let mut loader = RulesFileLoader::default();
+        let mut rules = RulesFileSchema::default();
+        let mut origins = Vec::new();
+
+        if rules_path_required || rules_path.exists() {
+            rules.merge(loader.load(rules_path)?)?;
+            origins.push(format!("'{}'", rules_path.display()));
+        }
+        if let Some(workspace_rules) = loader.load_workspace_metadata(metadata)? {
+            rules.merge_source(workspace_rules).with_context(|| {
+                format!(
+                    "invalid dependency rules in {} and [workspace.metadata.deprule]",
+                    origins.join(" and ")
+                )
+            })?;
+            origins.push("[workspace.metadata.deprule]".to_string());
+        }
+        if let Some(package_rules) = RulesFileSchema::from_package_metadata(metadata)? {
+            rules.merge(package_rules)?;
+            origins.push("[package.metadata.deprule]".to_string());
+        }
+
+        if origins.is_empty() {
+            bail!(
+                "no dependency rules found: create '{}' or add [workspace.metadata.deprule] or [package.metadata.deprule] to a Cargo.toml",
+                rules_path.display()
+            );
+        }
+
+        rules
+            .try_into()
+            .with_context(|| format!("invalid dependency rules in {}", origins.join(" and ")))
+    }
+
+    /// Reads a rules file, following its `extends` and `include` entries.
+    #[cfg(test)]
     #[tracing::instrument(skip_all, fields(path = ?path.as_ref()))]
     pub(crate) fn from_file<P>(path: P) -> Result<DependencyRules, Error>
     where
         P: AsRef<std::path::Path>,
     {
         let path = path.as_ref();
-        let rules_text: String = fs::read_to_string(path).with_context(|| {
-            format!("failed to read dependency rules from '{}'", path.display())
-        })?;
-        let rules: RulesFileSchema = toml::from_str(&rules_text)
-            .with_context(|| format!("failed to parse dependency rules in '{}'", path.display()))?;
+        let rules = RulesFileLoader::default().load(path)?;
 
         rules
             .try_into()
@@ -45,18 +404,45 @@ impl DependencyRules {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metadata::{CollectMetadataConfig, collect_metadata};
+
+    fn workspace_metadata(manifest_path: &str) -> Metadata {
+        collect_metadata(CollectMetadataConfig {
+            manifest_path: Some(manifest_path.to_string()),
+            ..CollectMetadataConfig::default()
+        })
+        .unwrap()
+    }
 
     #[test]
     fn test_read_dependency_rule_from_file() {
         let path = "tests/test_files/parse_rules_test.toml";
         let expected = DependencyRules {
             rules: vec![DependencyRule {
-                package: "package1".to_string(),
-                forbidden_dependencies: HashSet::from([
-                    "package2".to_string(),
-                    "package3".to_string(),
-                ]),
+                package: PackageMatcher::Exact("package1".to_string()),
+                forbidden_dependencies: vec![
+                    PackageMatcher::Exact("package2".to_string()).into(),
+                    PackageMatcher::Exact("package3".to_string()).into(),
+                ],
+                allowed_dependencies: None,
+                required_versions: Vec::new(),
+                transitive: false,
+                forbidden_features: BTreeMap::new(),
+                no_default_features: Vec::new(),
+                kinds: vec![
+                    DependencyKind::Normal,
+                    DependencyKind::Build,
+                    DependencyKind::Development,
+                ],
+                platforms: None,
+                sources: None,
+                forbidden_sources: Vec::new(),
+                severity: Severity::Error,
+                reason: None,
+                help: None,
+                origin: Some(PathBuf::from(path)),
             }],
+            ..DependencyRules::default()
         };
 
         let actual = DependencyRules::from_file(path).unwrap();
@@ -94,4 +480,260 @@ mod tests {
 
         std::fs::remove_dir_all(&dir).unwrap();
     }
+
+    #[test]
+    fn test_from_file_extends_and_include() {
+        let rules =
+            DependencyRules::from_file("tests/test_files/compose/dependency_rules.toml").unwrap();
+
+        let packages: Vec<String> = rules
+            .rules
+            .iter()
+            .map(|rule| rule.package.to_string())
+            .collect();
+        assert_eq!(
+            packages,
+            vec![
+                "tangled-ca-core",
+                "tangled-ca-interactor",
+                "tangled-ca-repositry"
+            ]
+        );
+        assert_eq!(rules.layers.len(), 2);
+        assert_eq!(
+            rules.rules[0].origin,
+            Some(PathBuf::from(
+                "tests/test_files/compose/../shared/deprule-base.toml"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_from_file_include_cycle() {
+        let result = DependencyRules::from_file("tests/test_files/include_cycle/first.toml");
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("include cycle detected"));
+        assert!(message.contains("first.toml -> "));
+        assert!(message.contains("second.toml -> "));
+    }
+
+    #[test]
+    fn test_from_file_missing_extended_file() {
+        let dir = std::env::temp_dir().join("check_deprule_test_missing_extends");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rules.toml");
+        std::fs::write(&path, "extends = [\"base.toml\"]").unwrap();
+
+        let result = DependencyRules::from_file(&path);
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("failed to read dependency rules from"));
+        assert!(message.contains("base.toml"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_duplicate_rule_across_files() {
+        let dir = std::env::temp_dir().join("check_deprule_test_duplicate_include");
+        std::fs::create_dir_all(&dir).unwrap();
+        let rule =
+            "[[rules.rule]]\npackage = \"ca-core\"\nforbidden_dependencies = [\"ca-handler\"]\n";
+        std::fs::write(dir.join("base.toml"), rule).unwrap();
+        std::fs::write(
+            dir.join("rules.toml"),
+            format!("extends = [\"base.toml\"]\n{rule}"),
+        )
+        .unwrap();
+
+        let result = DependencyRules::from_file(dir.join("rules.toml"));
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("duplicate rule definition for package 'ca-core' in '"));
+        assert!(message.contains("rules.toml'"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_including_file_takes_precedence() {
+        let dir = std::env::temp_dir().join("check_deprule_test_precedence");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.toml"),
+            "[no_cycles]\nseverity = \"warning\"\n[no_duplicate_versions]\nseverity = \"warning\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("included.toml"),
+            "[no_cycles]\nseverity = \"info\"\n[no_duplicate_versions]\nseverity = \"info\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("rules.toml"),
+            "extends = [\"base.toml\"]\ninclude = [\"included.toml\"]\n[no_cycles]\nseverity = \"error\"\n",
+        )
+        .unwrap();
+
+        let rules = DependencyRules::from_file(dir.join("rules.toml")).unwrap();
+        assert_eq!(rules.no_cycles.unwrap().severity, Severity::Error);
+        assert_eq!(
+            rules.no_duplicate_versions.unwrap().severity,
+            Severity::Info
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_conflicting_groups() {
+        let dir = std::env::temp_dir().join("check_deprule_test_conflicting_groups");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("base.toml"), "[groups]\ndatabase = [\"sqlx\"]\n").unwrap();
+        std::fs::write(
+            dir.join("rules.toml"),
+            "extends = [\"base.toml\"]\n[groups]\ndatabase = [\"diesel\"]\n",
+        )
+        .unwrap();
+
+        let result = DependencyRules::from_file(dir.join("rules.toml"));
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rules.toml'"));
+        assert!(message.contains("group 'database' is defined more than once"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_for_workspace_reads_workspace_metadata() {
+        let metadata = workspace_metadata("tests/demo_crates/metadata-rules/Cargo.toml");
+        let rules = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/demo_crates/metadata-rules/dependency_rules.toml"),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.rules[0].package.to_string(), "meta-core");
+        assert!(
+            rules.rules[0]
+                .origin
+                .as_ref()
+                .is_some_and(|origin| origin.ends_with("metadata-rules/Cargo.toml"))
+        );
+    }
+
+    #[test]
+    fn test_for_workspace_merges_rules_file_first() {
+        let metadata = workspace_metadata("tests/demo_crates/metadata-rules/Cargo.toml");
+        let rules = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/test_files/metadata_merge_rules_test.toml"),
+            true,
+        )
+        .unwrap();
+
+        let packages: Vec<String> = rules
+            .rules
+            .iter()
+            .map(|rule| rule.package.to_string())
+            .collect();
+        assert_eq!(packages, vec!["meta-handler", "meta-core", "meta-app"]);
+    }
+
+    #[test]
+    fn test_for_workspace_rejects_rule_defined_in_both() {
+        let metadata = workspace_metadata("tests/demo_crates/metadata-rules/Cargo.toml");
+        let result = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/test_files/metadata_duplicate_rules_test.toml"),
+            true,
+        );
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("and [workspace.metadata.deprule]"));
+        assert!(message.contains("duplicate rule definition for package 'meta-core'"));
+    }
+
+    #[test]
+    fn test_for_workspace_rejects_no_cycles_defined_in_both() {
+        let metadata = workspace_metadata("tests/demo_crates/metadata-rules/Cargo.toml");
+        let result = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/test_files/metadata_no_cycles_rules_test.toml"),
+            true,
+        );
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("and [workspace.metadata.deprule]"));
+        assert!(message.contains("[no_cycles] may only be defined in one place"));
+    }
+
+    #[test]
+    fn test_for_workspace_without_any_rules() {
+        let metadata = workspace_metadata("tests/demo_crates/clean-arch/Cargo.toml");
+
+        let result = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/demo_crates/clean-arch/missing_rules.toml"),
+            false,
+        );
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("no dependency rules found")
+        );
+
+        // an explicitly requested rules file must exist
+        let result = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/demo_crates/clean-arch/missing_rules.toml"),
+            true,
+        );
+        assert!(
+            format!("{:#}", result.unwrap_err()).contains("failed to read dependency rules from")
+        );
+    }
+
+    #[test]
+    fn test_for_workspace_reads_package_metadata() {
+        let metadata = workspace_metadata("tests/demo_crates/metadata-rules/Cargo.toml");
+        let rules = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/demo_crates/metadata-rules/dependency_rules.toml"),
+            false,
+        )
+        .unwrap();
+
+        let rule = rules
+            .rules
+            .iter()
+            .find(|rule| rule.package == PackageMatcher::Exact("meta-app".to_string()))
+            .expect("member rule should default to the member package");
+        assert_eq!(
+            rule.allowed_dependencies,
+            Some(vec![PackageMatcher::Exact("meta-core".to_string()).into()])
+        );
+        assert!(
+            rule.origin
+                .as_ref()
+                .is_some_and(|origin| origin.ends_with("metadata-rules/app/Cargo.toml"))
+        );
+    }
+
+    #[test]
+    fn test_for_workspace_rejects_package_rule_also_in_rules_file() {
+        let metadata = workspace_metadata("tests/demo_crates/metadata-rules/Cargo.toml");
+        let result = DependencyRules::for_workspace(
+            &metadata,
+            Path::new("tests/test_files/package_metadata_duplicate_rules_test.toml"),
+            true,
+        );
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("and [package.metadata.deprule]"));
+        assert!(message.contains("duplicate rule definition for package 'meta-app' in '"));
+        assert!(message.contains("app/Cargo.toml'"));
+    }
 }
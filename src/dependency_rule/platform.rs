@@ -0,0 +1,117 @@
+use anyhow::{Context, Error, bail};
+use cargo_platform::{Cfg, CfgExpr, Platform};
+use std::fmt;
+
+/// A compiled `platforms` entry of a rule: a target triple, or a `cfg(...)`
+/// describing the platform by the names and key-value pairs set on it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PlatformMatcher {
+    Target(String),
+    Cfg {
+        expression: CfgExpr,
+        facts: Vec<Cfg>,
+    },
+}
+
+impl PlatformMatcher {
+    /// Parses `wasm32-unknown-unknown`, `cfg(windows)` or
+    /// `cfg(all(target_arch = "wasm32", target_os = "unknown"))`.
+    pub(crate) fn new(text: &str) -> Result<Self, Error> {
+        let platform: Platform = text
+            .parse()
+            .with_context(|| format!("invalid platform '{text}'"))?;
+        match platform {
+            Platform::Name(name) => Ok(PlatformMatcher::Target(name)),
+            Platform::Cfg(expression) => {
+                let mut facts = Vec::new();
+                if !collect_facts(&expression, &mut facts) {
+                    bail!(
+                        "platform '{text}' may only combine names and key-value pairs with all()"
+                    );
+                }
+                Ok(PlatformMatcher::Cfg { expression, facts })
+            }
+        }
+    }
+
+    /// Whether an edge declared for `platform` is active on this platform.
+    /// The cfg values of a target triple are not known, so conditional edges
+    /// are assumed to be active on every target triple.
+    pub(crate) fn enables(&self, platform: &Platform) -> bool {
+        match (self, platform) {
+            (PlatformMatcher::Target(name), Platform::Name(target)) => name == target,
+            (PlatformMatcher::Target(_), Platform::Cfg(_)) => true,
+            (PlatformMatcher::Cfg { .. }, Platform::Name(_)) => false,
+            (PlatformMatcher::Cfg { facts, .. }, Platform::Cfg(expression)) => {
+                expression.matches(facts)
+            }
+        }
+    }
+}
+
+fn collect_facts(expression: &CfgExpr, facts: &mut Vec<Cfg>) -> bool {
+    match expression {
+        CfgExpr::Value(cfg) => {
+            facts.push(cfg.clone());
+            true
+        }
+        CfgExpr::All(expressions) => expressions
+            .iter()
+            .all(|expression| collect_facts(expression, facts)),
+        CfgExpr::Not(_) | CfgExpr::Any(_) => false,
+    }
+}
+
+impl fmt::Display for PlatformMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlatformMatcher::Target(name) => f.write_str(name),
+            PlatformMatcher::Cfg { expression, .. } => write!(f, "cfg({expression})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn platform(text: &str) -> Platform {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cfg_platform_matcher() {
+        let wasm = PlatformMatcher::new(r#"cfg(target_arch = "wasm32")"#).unwrap();
+
+        assert!(wasm.enables(&platform(r#"cfg(target_arch = "wasm32")"#)));
+        assert!(wasm.enables(&platform("cfg(not(windows))")));
+        assert!(!wasm.enables(&platform("cfg(windows)")));
+        assert!(!wasm.enables(&platform("x86_64-pc-windows-msvc")));
+        assert_eq!(wasm.to_string(), r#"cfg(target_arch = "wasm32")"#);
+
+        let wasi = PlatformMatcher::new(r#"cfg(all(target_arch = "wasm32", target_os = "wasi"))"#)
+            .unwrap();
+        assert!(wasi.enables(&platform(r#"cfg(any(unix, target_os = "wasi"))"#)));
+    }
+
+    #[test]
+    fn test_target_platform_matcher() {
+        let windows = PlatformMatcher::new("x86_64-pc-windows-msvc").unwrap();
+
+        assert!(windows.enables(&platform("x86_64-pc-windows-msvc")));
+        assert!(!windows.enables(&platform("wasm32-unknown-unknown")));
+        assert!(windows.enables(&platform("cfg(unix)")));
+    }
+
+    #[test]
+    fn test_invalid_platform_matchers() {
+        let message = format!("{:#}", PlatformMatcher::new("cfg(windows").unwrap_err());
+        assert!(message.contains("invalid platform 'cfg(windows'"));
+
+        let message = format!(
+            "{:#}",
+            PlatformMatcher::new("cfg(not(windows))").unwrap_err()
+        );
+        assert!(message.contains("may only combine names and key-value pairs with all()"));
+    }
+}
@@ -1,303 +1,2527 @@
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
 
-use super::{DependencyRule, DependencyRules};
-use anyhow::{Error, bail};
+use super::license::license_id;
+use super::{
+    Budget, DependencyMatcher, DependencyRule, DependencyRules, Exception, Facade, Layer,
+    LicenseException, LicensePolicy, NoCycles, NoDuplicateVersions, PackageMatcher,
+    PlatformMatcher, Severity, SourceMatcher,
+};
+use anyhow::{Context, Error, anyhow, bail};
+use cargo_metadata::{DependencyKind, Metadata};
+use chrono::NaiveDate;
+use semver::VersionReq;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
 pub struct RulesFileSchema {
+    /// Rules files loaded before this one, relative to this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extends: Vec<String>,
+    /// Glob patterns of rules files loaded after this one, relative to this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
+    /// Alternative registry names usable in `registry:<name>` source selectors.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    registries: BTreeMap<String, String>,
+    /// Named lists of package selectors, referenced as `@name`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, Vec<MatcherSchema>>,
     rules: Option<RulesSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<LayerSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exceptions: Vec<ExceptionSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    no_cycles: Option<NoCyclesSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    budgets: Vec<BudgetSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    facades: Vec<FacadeSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    no_duplicate_versions: Option<NoDuplicateVersionsSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    licenses: Option<LicensesSchema>,
+}
+
+impl RulesFileSchema {
+    /// Appends the rules of `other`. Its `[no_cycles]`, `[licenses]` and
+    /// `[no_duplicate_versions]` replace those merged before, while groups
+    /// and registries must not be defined differently in both.
+    pub(super) fn merge(&mut self, other: RulesFileSchema) -> Result<(), Error> {
+        for (name, url) in other.registries {
+            match self.registries.get(&name) {
+                Some(existing) if *existing != url => {
+                    bail!("registry '{name}' is defined as both '{existing}' and '{url}'")
+                }
+                _ => {
+                    self.registries.insert(name, url);
+                }
+            }
+        }
+        for (name, members) in other.groups {
+            match self.groups.get(&name) {
+                Some(existing) if *existing != members => {
+                    bail!("group '{name}' is defined more than once with different packages")
+                }
+                _ => {
+                    self.groups.insert(name, members);
+                }
+            }
+        }
+        if let Some(other_rules) = other.rules {
+            self.rules
+                .get_or_insert_with(|| RulesSchema { rule: Vec::new() })
+                .rule
+                .extend(other_rules.rule);
+        }
+        self.layers.extend(other.layers);
+        self.exceptions.extend(other.exceptions);
+        if other.no_cycles.is_some() {
+            self.no_cycles = other.no_cycles;
+        }
+        self.budgets.extend(other.budgets);
+        self.facades.extend(other.facades);
+        if other.no_duplicate_versions.is_some() {
+            self.no_duplicate_versions = other.no_duplicate_versions;
+        }
+        if other.licenses.is_some() {
+            self.licenses = other.licenses;
+        }
+        Ok(())
+    }
+
+    /// Merges the rules of another source of the same workspace, such as
+    /// `[workspace.metadata.deprule]`. Unlike extended and included files,
+    /// another source may not override the layers, `[no_cycles]`,
+    /// `[licenses]` or `[no_duplicate_versions]` merged before.
+    pub(super) fn merge_source(&mut self, other: RulesFileSchema) -> Result<(), Error> {
+        let defined_in_both = [
+            (
+                "[[layers]]",
+                !self.layers.is_empty() && !other.layers.is_empty(),
+            ),
+            (
+                "[no_cycles]",
+                self.no_cycles.is_some() && other.no_cycles.is_some(),
+            ),
+            (
+                "[licenses]",
+                self.licenses.is_some() && other.licenses.is_some(),
+            ),
+            (
+                "[no_duplicate_versions]",
+                self.no_duplicate_versions.is_some() && other.no_duplicate_versions.is_some(),
+            ),
+        ];
+        if let Some((name, _)) = defined_in_both.iter().find(|(_, both)| *both) {
+            bail!("{name} may only be defined in one place");
+        }
+        self.merge(other)
+    }
+}
+
+impl RulesFileSchema {
+    /// Records `path` as the origin of the layers, budgets, facades and
+    /// settings of this file.
+    fn set_origin(&mut self, path: &Path) {
+        let origin = || Some(path.to_path_buf());
+        for layer in &mut self.layers {
+            layer.origin = origin();
+        }
+        for budget in &mut self.budgets {
+            budget.origin = origin();
+        }
+        for facade in &mut self.facades {
+            facade.origin = origin();
+        }
+        if let Some(no_cycles) = &mut self.no_cycles {
+            no_cycles.origin = origin();
+        }
+        if let Some(no_duplicates) = &mut self.no_duplicate_versions {
+            no_duplicates.origin = origin();
+        }
+        if let Some(licenses) = &mut self.licenses {
+            licenses.origin = origin();
+        }
+    }
+
+    /// Collects the rules declared in `[package.metadata.deprule]` of the
+    /// workspace members. Returns `None` when no member declares one.
+    pub(super) fn from_package_metadata(metadata: &Metadata) -> Result<Option<Self>, Error> {
+        let mut rules = Vec::new();
+        for package in metadata.workspace_packages() {
+            let Some(value) = package.metadata.get("deprule") else {
+                continue;
+            };
+            let mut rule = RuleSchema::deserialize(value).with_context(|| {
+                format!(
+                    "failed to parse [package.metadata.deprule] in '{}'",
+                    package.manifest_path
+                )
+            })?;
+            // members that only declare labels have no rule of their own
+            if rule == RuleSchema::default() {
+                continue;
+            }
+            if rule.package.is_none() && rule.package_regex.is_none() {
+                rule.package = Some(MatcherSchema::Name(package.name.to_string()));
+            }
+            rule.origin = Some(package.manifest_path.clone().into_std_path_buf());
+            rules.push(rule);
+        }
+
+        Ok((!rules.is_empty()).then(|| RulesFileSchema {
+            rules: Some(RulesSchema { rule: rules }),
+            ..RulesFileSchema::default()
+        }))
+    }
+}
+
+/// Reads rules files and follows `extends` and `include` recursively. Files
+/// reached twice through different branches are only merged once, while a
+/// file reaching itself is reported as a cycle.
+#[derive(Default)]
+pub(super) struct RulesFileLoader {
+    stack: Vec<PathBuf>,
+    loaded: HashSet<PathBuf>,
+}
+
+impl RulesFileLoader {
+    pub(super) fn load(&mut self, path: &Path) -> Result<RulesFileSchema, Error> {
+        let Some(canonical) = self.enter(path)? else {
+            return Ok(RulesFileSchema::default());
+        };
+
+        let rules_text = fs::read_to_string(path).with_context(|| {
+            format!("failed to read dependency rules from '{}'", path.display())
+        })?;
+        let rules_file: RulesFileSchema = toml::from_str(&rules_text)
+            .with_context(|| format!("failed to parse dependency rules in '{}'", path.display()))?;
+
+        self.resolve(rules_file, path, canonical)
+    }
+
+    /// Reads `[workspace.metadata.deprule]`, resolving paths relative to the
+    /// workspace manifest. Returns `None` when the table is absent.
+    pub(super) fn load_workspace_metadata(
+        &mut self,
+        metadata: &Metadata,
+    ) -> Result<Option<RulesFileSchema>, Error> {
+        let Some(value) = metadata.workspace_metadata.get("deprule") else {
+            return Ok(None);
+        };
+        let manifest_path = metadata
+            .workspace_root
+            .join("Cargo.toml")
+            .into_std_path_buf();
+        let Some(canonical) = self.enter(&manifest_path)? else {
+            return Ok(None);
+        };
+
+        let rules_file = RulesFileSchema::deserialize(value).with_context(|| {
+            format!(
+                "failed to parse [workspace.metadata.deprule] in '{}'",
+                manifest_path.display()
+            )
+        })?;
+
+        self.resolve(rules_file, &manifest_path, canonical)
+            .map(Some)
+    }
+
+    /// Returns the canonical path of a file that has not been loaded yet.
+    fn enter(&mut self, path: &Path) -> Result<Option<PathBuf>, Error> {
+        let canonical = path.canonicalize().with_context(|| {
+            format!("failed to read dependency rules from '{}'", path.display())
+        })?;
+        if let Some(start) = self.stack.iter().position(|p| *p == canonical) {
+            let cycle = self.stack[start..]
+                .iter()
+                .chain([&canonical])
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            bail!("include cycle detected: {cycle}");
+        }
+
+        Ok(self.loaded.insert(canonical.clone()).then_some(canonical))
+    }
+
+    fn resolve(
+        &mut self,
+        mut rules_file: RulesFileSchema,
+        path: &Path,
+        canonical: PathBuf,
+    ) -> Result<RulesFileSchema, Error> {
+        for rule in rules_file
+            .rules
+            .iter_mut()
+            .flat_map(|rules| &mut rules.rule)
+        {
+            rule.origin = Some(path.to_path_buf());
+        }
+        rules_file.set_origin(path);
+
+        self.stack.push(canonical);
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let extends = std::mem::take(&mut rules_file.extends);
+        let include = std::mem::take(&mut rules_file.include);
+
+        // the file itself is merged last, so that its settings win over the
+        // files it extends or includes
+        let mut merged = RulesFileSchema::default();
+        for extended in extends {
+            let extended = base_dir.join(extended);
+            merged
+                .merge(self.load(&extended)?)
+                .with_context(|| format!("failed to extend '{}'", extended.display()))?;
+        }
+        for pattern in include {
+            for included in glob_relative(base_dir, &pattern)? {
+                merged
+                    .merge(self.load(&included)?)
+                    .with_context(|| format!("failed to include '{}'", included.display()))?;
+            }
+        }
+        merged
+            .merge(rules_file)
+            .with_context(|| format!("failed to merge rules in '{}'", path.display()))?;
+        self.stack.pop();
+
+        Ok(merged)
+    }
+}
+
+fn glob_relative(base_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let full_pattern = if Path::new(pattern).is_absolute() {
+        pattern.to_string()
+    } else {
+        let base_dir = base_dir.to_str().ok_or_else(|| {
+            anyhow!(
+                "rules directory '{}' is not valid UTF-8",
+                base_dir.display()
+            )
+        })?;
+        Path::new(&glob::Pattern::escape(base_dir))
+            .join(pattern)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    glob::glob(&full_pattern)
+        .map_err(|err| anyhow!("invalid include pattern '{pattern}': {err}"))?
+        .map(|entry| entry.with_context(|| format!("failed to expand include '{pattern}'")))
+        .collect()
 }
 
 impl TryFrom<RulesFileSchema> for DependencyRules {
     type Error = Error;
 
     fn try_from(rules_file: RulesFileSchema) -> Result<Self, Self::Error> {
-        let Some(rules) = rules_file.rules else {
-            return Ok(Self { rules: Vec::new() });
-        };
+        let rules = rules_file.rules.map(|rules| rules.rule).unwrap_or_default();
 
-        validate_rules(&rules.rule)?;
+        validate_rules(&rules)?;
+        validate_layers(&rules_file.layers)?;
+        let groups = compile_groups(&rules_file.groups)?;
 
         let dependency_rules = rules
-            .rule
             .into_iter()
             .map(|rule| {
-                let forbidden_dependencies = HashSet::from_iter(rule.forbidden_dependencies);
-                DependencyRule::new(rule.package, forbidden_dependencies)
+                compile_rule(&rule, &rules_file.registries, &groups)
+                    .with_context(|| format!("rule for package {}", rule.location()))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let layers = rules_file
+            .layers
+            .into_iter()
+            .map(|layer| {
+                let packages = layer
+                    .packages
+                    .iter()
+                    .map(|package| package.compile_unversioned(&groups))
+                    .collect::<Result<_, Error>>()
+                    .with_context(|| format!("layer '{}'", layer.name))?;
+                Ok(Layer {
+                    severity: layer.severity.unwrap_or_default(),
+                    origin: layer.origin,
+                    ..Layer::new(layer.name, packages, layer.strict)
+                })
             })
-            .collect();
+            .collect::<Result<_, Error>>()?;
+
+        let exceptions = rules_file
+            .exceptions
+            .iter()
+            .map(|exception| {
+                compile_exception(exception, &groups).with_context(|| {
+                    format!(
+                        "exception for '{} -> {}'",
+                        exception.package, exception.dependency
+                    )
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let no_cycles = rules_file
+            .no_cycles
+            .map(|no_cycles| {
+                if no_cycles.kinds.as_ref().is_some_and(Vec::is_empty) {
+                    bail!("no_cycles: kinds must not be empty");
+                }
+                Ok(NoCycles {
+                    kinds: match no_cycles.kinds {
+                        Some(kinds) => kinds.iter().map(|kind| kind.to_dependency_kind()).collect(),
+                        None => DependencyKindSchema::all(),
+                    },
+                    severity: no_cycles.severity.unwrap_or_default(),
+                    reason: no_cycles.reason,
+                    origin: no_cycles.origin,
+                })
+            })
+            .transpose()?;
+
+        let budgets = rules_file
+            .budgets
+            .iter()
+            .map(|budget| {
+                compile_budget(budget, &groups)
+                    .with_context(|| format!("budget for '{}'", budget.package))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let facades = rules_file
+            .facades
+            .iter()
+            .map(|facade| {
+                compile_facade(facade, &groups).with_context(|| {
+                    format!("facade for '{} -> {}'", facade.package, facade.target)
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let no_duplicate_versions = rules_file
+            .no_duplicate_versions
+            .map(|no_duplicates| {
+                compile_no_duplicate_versions(no_duplicates, &groups)
+                    .context("no_duplicate_versions")
+            })
+            .transpose()?;
+
+        let licenses = rules_file
+            .licenses
+            .map(|licenses| compile_licenses(licenses, &groups).context("licenses"))
+            .transpose()?;
 
         Ok(Self {
             rules: dependency_rules,
+            layers,
+            exceptions,
+            no_cycles,
+            budgets,
+            facades,
+            no_duplicate_versions,
+            licenses,
+        })
+    }
+}
+
+fn compile_rule(
+    rule: &RuleSchema,
+    registries: &BTreeMap<String, String>,
+    groups: &Groups,
+) -> Result<DependencyRule, Error> {
+    let package = match (&rule.package, &rule.package_regex) {
+        (Some(package), None) => package.compile_unversioned(groups)?,
+        (None, Some(regex)) => PackageMatcher::regex(regex)?,
+        _ => unreachable!("package selectors are checked by validate_rules"),
+    };
+    let forbidden_dependencies = rule
+        .forbidden_dependencies
+        .iter()
+        .map(|matcher| matcher.compile(groups))
+        .collect::<Result<_, Error>>()?;
+    let allowed_dependencies = rule
+        .allowed_dependencies
+        .as_ref()
+        .map(|allowed| {
+            allowed
+                .iter()
+                .map(|matcher| matcher.compile(groups))
+                .collect::<Result<_, Error>>()
+        })
+        .transpose()?;
+    let required_versions = rule
+        .required_versions
+        .iter()
+        .map(|matcher| matcher.compile(groups))
+        .collect::<Result<_, Error>>()?;
+
+    let no_default_features = rule
+        .no_default_features
+        .iter()
+        .map(|matcher| matcher.compile_unversioned(groups))
+        .collect::<Result<_, Error>>()?;
+
+    let platforms = rule
+        .platforms
+        .as_ref()
+        .map(|platforms| {
+            platforms
+                .iter()
+                .map(|platform| PlatformMatcher::new(platform))
+                .collect::<Result<_, Error>>()
+        })
+        .transpose()?;
+
+    let sources = rule
+        .sources
+        .as_ref()
+        .map(|sources| {
+            sources
+                .iter()
+                .map(|source| SourceMatcher::new(source, registries))
+                .collect::<Result<_, Error>>()
+        })
+        .transpose()?;
+    let forbidden_sources = rule
+        .forbid_sources
+        .iter()
+        .map(|source| SourceMatcher::new(source, registries))
+        .collect::<Result<_, Error>>()?;
+
+    let default_rule = DependencyRule::new(package, forbidden_dependencies);
+    let kinds = match &rule.kinds {
+        Some(kinds) => kinds.iter().map(|kind| kind.to_dependency_kind()).collect(),
+        None => default_rule.kinds.clone(),
+    };
+
+    Ok(DependencyRule {
+        allowed_dependencies,
+        required_versions,
+        transitive: rule.transitive,
+        forbidden_features: rule.forbidden_features.clone(),
+        no_default_features,
+        kinds,
+        platforms,
+        sources,
+        forbidden_sources,
+        severity: rule.severity.unwrap_or_default(),
+        reason: rule.reason.clone(),
+        help: rule.help.clone(),
+        origin: rule.origin.clone(),
+        ..default_rule
+    })
+}
+
+fn compile_exception(exception: &ExceptionSchema, groups: &Groups) -> Result<Exception, Error> {
+    if exception.package.is_empty() || exception.dependency.is_empty() {
+        bail!("package and dependency must not be empty");
+    }
+    if exception.reason.trim().is_empty() {
+        bail!("reason must not be empty");
+    }
+
+    Ok(Exception {
+        package: exception.package.compile_unversioned(groups)?,
+        dependency: exception.dependency.compile_unversioned(groups)?,
+        reason: exception.reason.clone(),
+        owner: exception.owner.clone(),
+        expires: exception.expires.date()?,
+    })
+}
+
+fn compile_budget(budget: &BudgetSchema, groups: &Groups) -> Result<Budget, Error> {
+    if budget.max_direct.is_none() && budget.max_transitive.is_none() {
+        bail!("set max_direct, max_transitive or both");
+    }
+    if budget.kinds.as_ref().is_some_and(Vec::is_empty) {
+        bail!("kinds must not be empty");
+    }
+
+    Ok(Budget {
+        package: budget.package.compile_unversioned(groups)?,
+        max_direct: budget.max_direct,
+        max_transitive: budget.max_transitive,
+        kinds: match &budget.kinds {
+            Some(kinds) => kinds.iter().map(|kind| kind.to_dependency_kind()).collect(),
+            None => DependencyKindSchema::all(),
+        },
+        severity: budget.severity.unwrap_or_default(),
+        reason: budget.reason.clone(),
+        origin: budget.origin.clone(),
+    })
+}
+
+fn compile_facade(facade: &FacadeSchema, groups: &Groups) -> Result<Facade, Error> {
+    if facade.only_via.is_empty() {
+        bail!("only_via must not be empty");
+    }
+    if facade.kinds.as_ref().is_some_and(Vec::is_empty) {
+        bail!("kinds must not be empty");
+    }
+
+    Ok(Facade {
+        package: facade.package.compile_unversioned(groups)?,
+        target: facade.target.compile_unversioned(groups)?,
+        only_via: facade
+            .only_via
+            .iter()
+            .map(|matcher| matcher.compile_unversioned(groups))
+            .collect::<Result<_, Error>>()?,
+        kinds: match &facade.kinds {
+            Some(kinds) => kinds.iter().map(|kind| kind.to_dependency_kind()).collect(),
+            None => DependencyKindSchema::all(),
+        },
+        severity: facade.severity.unwrap_or_default(),
+        reason: facade.reason.clone(),
+        origin: facade.origin.clone(),
+    })
+}
+
+fn compile_no_duplicate_versions(
+    no_duplicates: NoDuplicateVersionsSchema,
+    groups: &Groups,
+) -> Result<NoDuplicateVersions, Error> {
+    if let Some((name, _)) = no_duplicates.allow.iter().find(|(_, count)| **count < 2) {
+        bail!("allow for '{name}' must be at least 2 versions");
+    }
+
+    Ok(NoDuplicateVersions {
+        skip: no_duplicates
+            .skip
+            .iter()
+            .map(|matcher| matcher.compile_unversioned(groups))
+            .collect::<Result<_, Error>>()?,
+        allow: no_duplicates.allow,
+        severity: no_duplicates.severity.unwrap_or_default(),
+        reason: no_duplicates.reason,
+        origin: no_duplicates.origin,
+    })
+}
+
+fn compile_licenses(licenses: LicensesSchema, groups: &Groups) -> Result<LicensePolicy, Error> {
+    let license_ids = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| license_id(name))
+            .collect::<Result<Vec<_>, Error>>()
+    };
+    let allow = license_ids(&licenses.allow)?;
+    let deny = license_ids(&licenses.deny)?;
+    if let Some(id) = allow.iter().find(|id| deny.contains(id)) {
+        bail!("license '{}' is both allowed and denied", id.name);
+    }
+
+    let exceptions = licenses
+        .exceptions
+        .iter()
+        .map(|exception| {
+            let compile = || -> Result<_, Error> {
+                Ok(LicenseException {
+                    package: exception.package.compile_unversioned(groups)?,
+                    allow: license_ids(&exception.allow)?,
+                })
+            };
+            compile().with_context(|| format!("exception for '{}'", exception.package))
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(LicensePolicy {
+        allow,
+        deny,
+        exceptions,
+        severity: licenses.severity.unwrap_or_default(),
+        reason: licenses.reason,
+        origin: licenses.origin,
+    })
+}
+
+/// Compiled groups by name.
+type Groups = HashMap<String, PackageMatcher>;
+
+/// Compiles every group, resolving `@name` references to other groups.
+fn compile_groups(definitions: &BTreeMap<String, Vec<MatcherSchema>>) -> Result<Groups, Error> {
+    let mut groups = Groups::new();
+    for name in definitions.keys() {
+        compile_group(name, definitions, &mut groups, &mut Vec::new())?;
+    }
+    Ok(groups)
+}
+
+fn compile_group(
+    name: &str,
+    definitions: &BTreeMap<String, Vec<MatcherSchema>>,
+    groups: &mut Groups,
+    stack: &mut Vec<String>,
+) -> Result<(), Error> {
+    if groups.contains_key(name) {
+        return Ok(());
+    }
+    if let Some(start) = stack.iter().position(|group| group == name) {
+        let cycle = stack[start..]
+            .iter()
+            .chain([&name.to_string()])
+            .map(|group| format!("@{group}"))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        bail!("group '{name}' contains itself: {cycle}");
+    }
+    let Some(entries) = definitions.get(name) else {
+        bail!("undefined group '@{name}'");
+    };
+    if name.is_empty() {
+        bail!("group has an empty name");
+    }
+
+    stack.push(name.to_string());
+    for entry in entries {
+        if let Some(reference) = entry.group_reference() {
+            compile_group(reference, definitions, groups, stack)
+                .with_context(|| format!("group '{name}'"))?;
+        }
+    }
+    stack.pop();
+
+    let members = entries
+        .iter()
+        .map(|entry| {
+            if entry.is_empty() {
+                bail!("group '{name}': package is empty");
+            }
+            entry.compile_unversioned(groups)
         })
+        .collect::<Result<_, Error>>()
+        .with_context(|| format!("group '{name}'"))?;
+    groups.insert(
+        name.to_string(),
+        PackageMatcher::Group {
+            name: name.to_string(),
+            members,
+        },
+    );
+    Ok(())
+}
+
+fn validate_rules(rules: &[RuleSchema]) -> Result<(), Error> {
+    let mut seen_packages = HashSet::new();
+
+    for rule in rules {
+        match (&rule.package, &rule.package_regex) {
+            (Some(_), Some(_)) => bail!(
+                "rule for package '{}': package and package_regex cannot be used together",
+                rule.package_label()
+            ),
+            (None, None) => bail!("rule has neither package nor package_regex"),
+            (Some(package), None) if package.is_empty() => {
+                bail!("rule has an empty package name");
+            }
+            (None, Some(regex)) if regex.is_empty() => {
+                bail!("rule has an empty package name");
+            }
+            _ => {}
+        }
+
+        if !seen_packages.insert(rule.package_label()) {
+            bail!("duplicate rule definition for package {}", rule.location());
+        }
+
+        for dep in &rule.forbidden_dependencies {
+            if dep.is_empty() {
+                bail!(
+                    "rule for package '{}': forbidden_dependency is empty",
+                    rule.package_label()
+                );
+            }
+        }
+
+        if let Some(package) = &rule.package
+            && rule.forbidden_dependencies.contains(package)
+        {
+            bail!(
+                "rule for package '{}': package cannot forbid itself",
+                rule.package_label()
+            );
+        }
+
+        for dep in &rule.required_versions {
+            let has_version = matches!(
+                dep,
+                MatcherSchema::Table(MatcherTableSchema {
+                    version: Some(_),
+                    ..
+                })
+            );
+            if !has_version {
+                bail!(
+                    "rule for package '{}': required_versions entry '{}' has no version",
+                    rule.package_label(),
+                    dep
+                );
+            }
+        }
+
+        if rule.kinds.as_ref().is_some_and(Vec::is_empty) {
+            bail!(
+                "rule for package '{}': kinds must not be empty",
+                rule.package_label()
+            );
+        }
+
+        if let Some((name, _)) = rule
+            .forbidden_features
+            .iter()
+            .find(|(name, features)| name.is_empty() || features.is_empty())
+        {
+            bail!(
+                "rule for package '{}': forbidden_features for '{name}' must name a crate and at least one feature",
+                rule.package_label()
+            );
+        }
+
+        if rule.platforms.as_ref().is_some_and(Vec::is_empty) {
+            bail!(
+                "rule for package '{}': platforms must not be empty",
+                rule.package_label()
+            );
+        }
+
+        if rule.transitive && rule.forbidden_dependencies.is_empty() {
+            bail!(
+                "rule for package '{}': transitive requires forbidden_dependencies",
+                rule.package_label()
+            );
+        }
+
+        for dep in rule.allowed_dependencies.iter().flatten() {
+            if dep.is_empty() {
+                bail!(
+                    "rule for package '{}': allowed_dependency is empty",
+                    rule.package_label()
+                );
+            }
+
+            if rule.forbidden_dependencies.contains(dep) {
+                bail!(
+                    "rule for package '{}': '{}' is both allowed and forbidden",
+                    rule.package_label(),
+                    dep
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_layers(layers: &[LayerSchema]) -> Result<(), Error> {
+    let mut seen_layers = HashSet::new();
+    let mut layer_of_package = HashMap::new();
+
+    for layer in layers {
+        if layer.name.is_empty() {
+            bail!("layer has an empty name");
+        }
+
+        if !seen_layers.insert(&layer.name) {
+            bail!("duplicate layer definition '{}'", layer.name);
+        }
+
+        for package in &layer.packages {
+            if package.is_empty() {
+                bail!("layer '{}': package is empty", layer.name);
+            }
+
+            if let Some(other) = layer_of_package.insert(package.to_string(), &layer.name) {
+                bail!(
+                    "layer '{}': package '{}' already belongs to layer '{}'",
+                    layer.name,
+                    package,
+                    other
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct RulesSchema {
+    rule: Vec<RuleSchema>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+struct RuleSchema {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package: Option<MatcherSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package_regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    forbidden_dependencies: Vec<MatcherSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_dependencies: Option<Vec<MatcherSchema>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    required_versions: Vec<MatcherSchema>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    transitive: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    forbidden_features: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    no_default_features: Vec<MatcherSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kinds: Option<Vec<DependencyKindSchema>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    platforms: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sources: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    forbid_sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+    /// Rules file the rule was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum DependencyKindSchema {
+    Normal,
+    Build,
+    Dev,
+}
+
+impl DependencyKindSchema {
+    fn all() -> Vec<DependencyKind> {
+        vec![
+            DependencyKind::Normal,
+            DependencyKind::Build,
+            DependencyKind::Development,
+        ]
+    }
+
+    fn to_dependency_kind(self) -> DependencyKind {
+        match self {
+            DependencyKindSchema::Normal => DependencyKind::Normal,
+            DependencyKindSchema::Build => DependencyKind::Build,
+            DependencyKindSchema::Dev => DependencyKind::Development,
+        }
+    }
+}
+
+impl RuleSchema {
+    /// Name of the package selector as written in the rules file, used to
+    /// identify the rule in error messages and duplicate checks.
+    fn package_label(&self) -> String {
+        match (&self.package, &self.package_regex) {
+            (Some(package), _) => package.to_string(),
+            (None, Some(regex)) => format!("/{regex}/"),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// The quoted package label, followed by the rules file when it is known.
+    fn location(&self) -> String {
+        match &self.origin {
+            Some(origin) => format!("'{}' in '{}'", self.package_label(), origin.display()),
+            None => format!("'{}'", self.package_label()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct LayerSchema {
+    name: String,
+    packages: Vec<MatcherSchema>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    strict: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    /// Rules file the layer was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct NoCyclesSchema {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kinds: Option<Vec<DependencyKindSchema>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Rules file the setting was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct LicensesSchema {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    deny: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exceptions: Vec<LicenseExceptionSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Rules file the policy was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct LicenseExceptionSchema {
+    package: MatcherSchema,
+    /// Licenses accepted for the package on top of `allow`. When empty, the
+    /// package is not checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allow: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct NoDuplicateVersionsSchema {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skip: Vec<MatcherSchema>,
+    /// Number of incompatible versions tolerated, by crate name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    allow: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Rules file the setting was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct BudgetSchema {
+    package: MatcherSchema,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_direct: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_transitive: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kinds: Option<Vec<DependencyKindSchema>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Rules file the budget was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct FacadeSchema {
+    package: MatcherSchema,
+    target: MatcherSchema,
+    /// Packages through which `package` may reach `target`.
+    only_via: Vec<MatcherSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kinds: Option<Vec<DependencyKindSchema>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Rules file the facade was read from.
+    #[serde(skip)]
+    origin: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ExceptionSchema {
+    package: MatcherSchema,
+    dependency: MatcherSchema,
+    reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    expires: ExpirySchema,
+}
+
+/// A TOML date such as `2026-12-31`, or the same date as a string, which is
+/// how dates written in `Cargo.toml` metadata reach us.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum ExpirySchema {
+    Date(toml::value::Datetime),
+    Text(String),
+}
+
+impl ExpirySchema {
+    fn date(&self) -> Result<NaiveDate, Error> {
+        let text = match self {
+            ExpirySchema::Date(datetime) => datetime.to_string(),
+            ExpirySchema::Text(text) => text.clone(),
+        };
+        NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .map_err(|err| anyhow!("invalid expiry date '{text}', expected YYYY-MM-DD: {err}"))
+    }
+}
+
+/// A dependency selector: either a package name (optionally a glob pattern)
+/// or a table such as `{ regex = "..." }` or `{ name = "...", version = "..." }`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum MatcherSchema {
+    Name(String),
+    Table(MatcherTableSchema),
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct MatcherTableSchema {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+impl fmt::Display for MatcherSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatcherSchema::Name(name) => f.write_str(name),
+            MatcherSchema::Table(table) => {
+                match (&table.name, &table.regex, &table.label) {
+                    (Some(name), _, _) => f.write_str(name)?,
+                    (None, Some(regex), _) => write!(f, "/{regex}/")?,
+                    (None, None, Some(label)) => write!(f, "label:{label}")?,
+                    (None, None, None) => {}
+                }
+                if let Some(version) = &table.version {
+                    write!(f, " {version}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl MatcherSchema {
+    fn is_empty(&self) -> bool {
+        match self {
+            MatcherSchema::Name(name) => name.is_empty(),
+            MatcherSchema::Table(table) => match (&table.name, &table.regex, &table.label) {
+                (Some(name), None, None) => name.is_empty(),
+                (None, Some(regex), None) => regex.is_empty(),
+                (None, None, Some(label)) => label.is_empty(),
+                _ => false,
+            },
+        }
+    }
+
+    /// Name of the group referenced by `@name`, if this selector is one.
+    fn group_reference(&self) -> Option<&str> {
+        match self {
+            MatcherSchema::Name(name)
+            | MatcherSchema::Table(MatcherTableSchema {
+                name: Some(name), ..
+            }) => name.strip_prefix('@'),
+            _ => None,
+        }
+    }
+
+    fn compile(&self, groups: &Groups) -> Result<DependencyMatcher, Error> {
+        let version = match self {
+            MatcherSchema::Table(MatcherTableSchema {
+                version: Some(version),
+                ..
+            }) => Some(VersionReq::parse(version).map_err(|err| {
+                anyhow!("invalid version requirement '{version}' for '{self}': {err}")
+            })?),
+            _ => None,
+        };
+
+        Ok(DependencyMatcher::new(
+            self.compile_package(groups)?,
+            version,
+        ))
+    }
+
+    fn compile_package(&self, groups: &Groups) -> Result<PackageMatcher, Error> {
+        if let Some(group) = self.group_reference() {
+            return groups
+                .get(group)
+                .cloned()
+                .ok_or_else(|| anyhow!("undefined group '@{group}'"));
+        }
+
+        match self {
+            MatcherSchema::Name(name) => PackageMatcher::new(name),
+            MatcherSchema::Table(table) => match (&table.name, &table.regex, &table.label) {
+                (Some(name), None, None) => PackageMatcher::new(name),
+                (None, Some(regex), None) => PackageMatcher::regex(regex),
+                (None, None, Some(label)) => Ok(PackageMatcher::Label(label.clone())),
+                _ => bail!("'{self}' must set exactly one of name, regex or label"),
+            },
+        }
+    }
+
+    /// Compiles a selector that does not accept a version range.
+    fn compile_unversioned(&self, groups: &Groups) -> Result<PackageMatcher, Error> {
+        if let MatcherSchema::Table(MatcherTableSchema {
+            version: Some(_), ..
+        }) = self
+        {
+            bail!("'{self}': version requirements are not supported here");
+        }
+
+        self.compile_package(groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_graph::Edge;
+    use crate::dependency_rule::matcher::tests::package;
+    use string_auto_indent::auto_indent;
+
+    fn edge(kind: DependencyKind, platforms: &[&str]) -> Edge {
+        Edge {
+            kind,
+            platforms: platforms
+                .iter()
+                .map(|platform| platform.parse().unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_try_from_rules_file_schema_to_dependency_rules() {
+        let rules_file = RulesFileSchema {
+            rules: Some(RulesSchema {
+                rule: vec![RuleSchema {
+                    package: Some(MatcherSchema::Name("package1".to_string())),
+                    package_regex: None,
+                    forbidden_dependencies: vec![
+                        MatcherSchema::Name("package2".to_string()),
+                        MatcherSchema::Name("package3".to_string()),
+                    ],
+                    ..RuleSchema::default()
+                }],
+            }),
+            ..RulesFileSchema::default()
+        };
+        let expected = DependencyRules {
+            rules: vec![DependencyRule::new(
+                PackageMatcher::Exact("package1".to_string()),
+                vec![
+                    PackageMatcher::Exact("package2".to_string()).into(),
+                    PackageMatcher::Exact("package3".to_string()).into(),
+                ],
+            )],
+            ..DependencyRules::default()
+        };
+
+        let dependency_rules = DependencyRules::try_from(rules_file).unwrap();
+        assert_eq!(dependency_rules, expected);
+    }
+
+    #[test]
+    fn test_parse_rules_text_inline_table() {
+        let rules_text = r#"
+            [rules]
+            rule = [
+                {package = "package1", forbidden_dependencies = ["package2", "package3"]},
+            ]
+            "#;
+        let expected = RulesFileSchema {
+            rules: Some(RulesSchema {
+                rule: vec![RuleSchema {
+                    package: Some(MatcherSchema::Name("package1".to_string())),
+                    package_regex: None,
+                    forbidden_dependencies: vec![
+                        MatcherSchema::Name("package2".to_string()),
+                        MatcherSchema::Name("package3".to_string()),
+                    ],
+                    ..RuleSchema::default()
+                }],
+            }),
+            ..RulesFileSchema::default()
+        };
+
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        assert_eq!(rules, expected);
+    }
+
+    #[test]
+    fn test_parse_rules_text_table() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package2", "package3"]
+
+            [[rules.rule]]
+            package = "package2"
+            forbidden_dependencies = ["package1"]
+            "#;
+        let expected = RulesFileSchema {
+            rules: Some(RulesSchema {
+                rule: vec![
+                    RuleSchema {
+                        package: Some(MatcherSchema::Name("package1".to_string())),
+                        package_regex: None,
+                        forbidden_dependencies: vec![
+                            MatcherSchema::Name("package2".to_string()),
+                            MatcherSchema::Name("package3".to_string()),
+                        ],
+                        ..RuleSchema::default()
+                    },
+                    RuleSchema {
+                        package: Some(MatcherSchema::Name("package2".to_string())),
+                        package_regex: None,
+                        forbidden_dependencies: vec![MatcherSchema::Name("package1".to_string())],
+                        ..RuleSchema::default()
+                    },
+                ],
+            }),
+            ..RulesFileSchema::default()
+        };
+
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        assert_eq!(rules, expected);
+    }
+
+    #[test]
+    fn test_serialize_rules_schema() {
+        let rules = RulesFileSchema {
+            rules: Some(RulesSchema {
+                rule: vec![
+                    RuleSchema {
+                        package: Some(MatcherSchema::Name("package1".to_string())),
+                        package_regex: None,
+                        forbidden_dependencies: vec![
+                            MatcherSchema::Name("package2".to_string()),
+                            MatcherSchema::Name("package3".to_string()),
+                        ],
+                        ..RuleSchema::default()
+                    },
+                    RuleSchema {
+                        package: Some(MatcherSchema::Name("package2".to_string())),
+                        package_regex: None,
+                        forbidden_dependencies: vec![MatcherSchema::Name("package1".to_string())],
+                        ..RuleSchema::default()
+                    },
+                ],
+            }),
+            ..RulesFileSchema::default()
+        };
+        let expected = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package2", "package3"]
+
+            [[rules.rule]]
+            package = "package2"
+            forbidden_dependencies = ["package1"]
+            "#;
+
+        let rules_text = toml::to_string(&rules).unwrap();
+        assert_eq!(auto_indent(expected).trim(), rules_text.trim(),);
+    }
+
+    #[test]
+    fn test_parse_empty_toml() {
+        let rules_text = "";
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        assert!(dependency_rules.rules.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rules_section_without_rule() {
+        let rules_text = "[rules]";
+        let result: Result<RulesFileSchema, _> = toml::from_str(rules_text);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_empty_forbidden_dependencies() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = []
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        assert_eq!(dependency_rules.rules.len(), 1);
+        assert!(dependency_rules.rules[0].forbidden_dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid_toml_syntax() {
+        let rules_text = "this is not valid toml {{{";
+        let result: Result<RulesFileSchema, _> = toml::from_str(rules_text);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_empty_package_name() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = ""
+            forbidden_dependencies = ["package2"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("empty package name")
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_forbidden_dependency_name() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package2", ""]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("forbidden_dependency is empty")
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicate_package_rules() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package2"]
+
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package3"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("duplicate rule definition for package 'package1'")
+        );
+    }
+
+    #[test]
+    fn test_validate_self_reference() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package1"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("cannot forbid itself")
+        );
+    }
+
+    #[test]
+    fn test_compile_glob_patterns() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "*-domain-*"
+            forbidden_dependencies = ["*-adapter-*", "tokio*"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert!(matches!(rule.package, PackageMatcher::Glob(_)));
+        assert_eq!(rule.package.to_string(), "*-domain-*");
+        assert_eq!(rule.forbidden_dependencies.len(), 2);
+        assert!(
+            rule.forbidden_dependencies
+                .iter()
+                .all(|dep| matches!(dep.package, PackageMatcher::Glob(_)))
+        );
+    }
+
+    #[test]
+    fn test_validate_malformed_package_pattern() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "billing-[domain"
+            forbidden_dependencies = ["package2"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rule for package 'billing-[domain'"));
+        assert!(message.contains("invalid glob pattern"));
+    }
+
+    #[test]
+    fn test_validate_malformed_forbidden_dependency_pattern() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = ["package2", "adapter-[*"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rule for package 'package1'"));
+        assert!(message.contains("invalid glob pattern 'adapter-[*'"));
+    }
+
+    #[test]
+    fn test_parse_regex_matchers() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package_regex = "^(api|web)-.+-v[0-9]+$"
+            forbidden_dependencies = ["tokio", { regex = "^sqlx(-.+)?$" }]
+            "#;
+        let expected = RulesFileSchema {
+            rules: Some(RulesSchema {
+                rule: vec![RuleSchema {
+                    package: None,
+                    package_regex: Some("^(api|web)-.+-v[0-9]+$".to_string()),
+                    forbidden_dependencies: vec![
+                        MatcherSchema::Name("tokio".to_string()),
+                        MatcherSchema::Table(MatcherTableSchema {
+                            regex: Some("^sqlx(-.+)?$".to_string()),
+                            ..MatcherTableSchema::default()
+                        }),
+                    ],
+                    ..RuleSchema::default()
+                }],
+            }),
+            ..RulesFileSchema::default()
+        };
+
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        assert_eq!(rules, expected);
+
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(
+            rule.package,
+            PackageMatcher::regex("^(api|web)-.+-v[0-9]+$").unwrap()
+        );
+        assert_eq!(
+            rule.forbidden_dependencies[1],
+            PackageMatcher::regex("^sqlx(-.+)?$").unwrap().into()
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_package_regex() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package_regex = "^(api|web"
+            forbidden_dependencies = ["package2"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rule for package '/^(api|web/'"));
+        assert!(message.contains("invalid regex"));
+    }
+
+    #[test]
+    fn test_validate_invalid_forbidden_dependency_regex() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = [{ regex = "sqlx-(" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rule for package 'package1'"));
+        assert!(message.contains("invalid regex 'sqlx-('"));
+    }
+
+    #[test]
+    fn test_validate_package_and_package_regex_together() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            package_regex = "^package"
+            forbidden_dependencies = ["package2"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("package and package_regex cannot be used together")
+        );
+    }
+
+    #[test]
+    fn test_validate_missing_package_selector() {
+        let rules_text = r#"
+            [[rules.rule]]
+            forbidden_dependencies = ["package2"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("neither package nor package_regex")
+        );
+    }
+
+    #[test]
+    fn test_parse_allowed_dependencies() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            allowed_dependencies = ["serde", "thiserror"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert!(rule.forbidden_dependencies.is_empty());
+        assert_eq!(
+            rule.allowed_dependencies,
+            Some(vec![
+                PackageMatcher::Exact("serde".to_string()).into(),
+                PackageMatcher::Exact("thiserror".to_string()).into(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_allowed_dependencies() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            allowed_dependencies = []
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        assert_eq!(dependency_rules.rules[0].allowed_dependencies, Some(vec![]));
+    }
+
+    #[test]
+    fn test_validate_conflicting_allowed_and_forbidden_dependencies() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            allowed_dependencies = ["serde", "thiserror"]
+            forbidden_dependencies = ["serde"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("'serde' is both allowed and forbidden")
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_allowed_dependency_name() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            allowed_dependencies = ["serde", ""]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("allowed_dependency is empty")
+        );
+    }
+
+    #[test]
+    fn test_parse_layers() {
+        let rules_text = r#"
+            [[layers]]
+            name = "handler"
+            packages = ["*-handler"]
+
+            [[layers]]
+            name = "use-case"
+            packages = ["*-interactor", { regex = "-usecase$" }]
+            strict = true
+
+            [[layers]]
+            name = "domain"
+            packages = ["*-core"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        assert!(dependency_rules.rules.is_empty());
+        assert_eq!(
+            dependency_rules.layers,
+            vec![
+                Layer::new(
+                    "handler".to_string(),
+                    vec![PackageMatcher::new("*-handler").unwrap()],
+                    false,
+                ),
+                Layer::new(
+                    "use-case".to_string(),
+                    vec![
+                        PackageMatcher::new("*-interactor").unwrap(),
+                        PackageMatcher::regex("-usecase$").unwrap(),
+                    ],
+                    true,
+                ),
+                Layer::new(
+                    "domain".to_string(),
+                    vec![PackageMatcher::new("*-core").unwrap()],
+                    false,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicate_layer_names() {
+        let rules_text = r#"
+            [[layers]]
+            name = "domain"
+            packages = ["ca-core"]
+
+            [[layers]]
+            name = "domain"
+            packages = ["ca-entity"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("duplicate layer definition 'domain'")
+        );
+    }
+
+    #[test]
+    fn test_validate_package_in_multiple_layers() {
+        let rules_text = r#"
+            [[layers]]
+            name = "use-case"
+            packages = ["ca-interactor"]
+
+            [[layers]]
+            name = "domain"
+            packages = ["ca-core", "ca-interactor"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("package 'ca-interactor' already belongs to layer 'use-case'")
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_layer_pattern() {
+        let rules_text = r#"
+            [[layers]]
+            name = "domain"
+            packages = ["ca-[core"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("layer 'domain'"));
+        assert!(message.contains("invalid glob pattern"));
+    }
+
+    #[test]
+    fn test_parse_transitive_rule() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            forbidden_dependencies = ["ca-handler"]
+            transitive = true
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        assert!(dependency_rules.rules[0].transitive);
+    }
+
+    #[test]
+    fn test_validate_transitive_without_forbidden_dependencies() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            allowed_dependencies = ["serde"]
+            transitive = true
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("transitive requires forbidden_dependencies")
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_kinds() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-interactor"
+            forbidden_dependencies = ["ca-database"]
+            kinds = ["normal", "build"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(
+            rule.kinds,
+            vec![DependencyKind::Normal, DependencyKind::Build]
+        );
+        assert!(rule.applies_to(&edge(DependencyKind::Normal, &[])));
+        assert!(!rule.applies_to(&edge(DependencyKind::Development, &[])));
+    }
+
+    #[test]
+    fn test_parse_rule_kinds_default_to_all() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-interactor"
+            forbidden_dependencies = ["ca-database"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert!(rule.applies_to(&edge(DependencyKind::Normal, &[])));
+        assert!(rule.applies_to(&edge(DependencyKind::Build, &[])));
+        assert!(rule.applies_to(&edge(DependencyKind::Development, &[])));
+    }
+
+    #[test]
+    fn test_parse_unknown_rule_kind() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-interactor"
+            forbidden_dependencies = ["ca-database"]
+            kinds = ["runtime"]
+            "#;
+        let result: Result<RulesFileSchema, _> = toml::from_str(rules_text);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_empty_rule_kinds() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-interactor"
+            forbidden_dependencies = ["ca-database"]
+            kinds = []
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("kinds must not be empty")
+        );
+    }
+
+    #[test]
+    fn test_parse_version_constraints() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "*"
+            forbidden_dependencies = [{ name = "openssl", version = "<0.10.60" }]
+            required_versions = [{ name = "tokio", version = ">=1.38" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(
+            rule.forbidden_dependencies,
+            vec![DependencyMatcher::new(
+                PackageMatcher::new("openssl").unwrap(),
+                Some(VersionReq::parse("<0.10.60").unwrap()),
+            )]
+        );
+        assert_eq!(
+            rule.required_versions,
+            vec![DependencyMatcher::new(
+                PackageMatcher::new("tokio").unwrap(),
+                Some(VersionReq::parse(">=1.38").unwrap()),
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_version_requirement() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = [{ name = "openssl", version = "not a version" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rule for package 'package1'"));
+        assert!(message.contains("invalid version requirement 'not a version'"));
+    }
+
+    #[test]
+    fn test_validate_required_versions_without_version() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            required_versions = ["tokio"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("required_versions entry 'tokio' has no version")
+        );
+    }
+
+    #[test]
+    fn test_validate_matcher_table_with_name_and_regex() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "package1"
+            forbidden_dependencies = [{ name = "tokio", regex = "^tokio" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            format!("{:#}", result.unwrap_err())
+                .contains("must set exactly one of name, regex or label")
+        );
+    }
+
+    #[test]
+    fn test_validate_version_in_layer_packages() {
+        let rules_text = r#"
+            [[layers]]
+            name = "domain"
+            packages = [{ name = "ca-core", version = "^1" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+        assert!(
+            format!("{:#}", result.unwrap_err())
+                .contains("version requirements are not supported here")
+        );
+    }
+
+    #[test]
+    fn test_parse_source_selectors() {
+        let rules_text = r#"
+            [registries]
+            internal = "sparse+https://cargo.internal.example/index/"
+
+            [[rules.rule]]
+            package = "release-*"
+            sources = ["crates-io", "registry:internal"]
+            forbid_sources = ["git", "path"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(
+            rule.sources,
+            Some(vec![
+                SourceMatcher::CratesIo,
+                SourceMatcher::Registry(Some(
+                    "sparse+https://cargo.internal.example/index".to_string()
+                )),
+            ])
+        );
+        assert_eq!(
+            rule.forbidden_sources,
+            vec![SourceMatcher::Git, SourceMatcher::Path]
+        );
+    }
+
+    #[test]
+    fn test_validate_unknown_registry() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "release-app"
+            sources = ["registry:internal"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let result = DependencyRules::try_from(rules);
+        assert!(result.is_err());
+
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("rule for package 'release-app'"));
+        assert!(message.contains("unknown registry 'internal'"));
+    }
+
+    #[test]
+    fn test_parse_severity() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            forbidden_dependencies = ["ca-handler"]
+            severity = "warning"
+
+            [[rules.rule]]
+            package = "ca-interactor"
+            forbidden_dependencies = ["ca-handler"]
+
+            [[layers]]
+            name = "domain"
+            packages = ["ca-core"]
+            severity = "info"
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        assert_eq!(dependency_rules.rules[0].severity, Severity::Warning);
+        assert_eq!(dependency_rules.rules[1].severity, Severity::Error);
+        assert_eq!(dependency_rules.layers[0].severity, Severity::Info);
+    }
+
+    #[test]
+    fn test_parse_unknown_severity() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            forbidden_dependencies = ["ca-handler"]
+            severity = "fatal"
+            "#;
+        let result: Result<RulesFileSchema, _> = toml::from_str(rules_text);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_label_selectors() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = { label = "domain" }
+            forbidden_dependencies = [{ label = "infrastructure" }, "tokio"]
+
+            [[layers]]
+            name = "pure"
+            packages = [{ label = "pure" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(rule.package, PackageMatcher::Label("domain".to_string()));
+        assert_eq!(
+            rule.forbidden_dependencies[0].package,
+            PackageMatcher::Label("infrastructure".to_string())
+        );
+        assert_eq!(
+            dependency_rules.layers[0].packages,
+            vec![PackageMatcher::Label("pure".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_validate_label_selector_errors() {
+        let rules_text = r#"
+            [[rules.rule]]
+            package = { label = "domain" }
+            forbidden_dependencies = [{ label = "domain" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("rule for package 'label:domain': package cannot forbid itself"));
+
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            forbidden_dependencies = [{ name = "ca-handler", label = "handler" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("must set exactly one of name, regex or label"));
     }
-}
 
-fn validate_rules(rules: &[RuleSchema]) -> Result<(), Error> {
-    let mut seen_packages = HashSet::new();
+    #[test]
+    fn test_parse_groups() {
+        let rules_text = r#"
+            [groups]
+            infra = ["ca-database", "ca-handler", "sqlx*", "@http"]
+            http = ["reqwest"]
 
-    for rule in rules {
-        if rule.package.is_empty() {
-            bail!("rule has an empty package name");
-        }
+            [[rules.rule]]
+            package = "ca-core"
+            forbidden_dependencies = ["@infra"]
 
-        if !seen_packages.insert(&rule.package) {
-            bail!("duplicate rule definition for package '{}'", rule.package);
-        }
+            [[rules.rule]]
+            package = "@http"
+            forbidden_dependencies = ["ca-core"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
 
-        for dep in &rule.forbidden_dependencies {
-            if dep.is_empty() {
-                bail!(
-                    "rule for package '{}': forbidden_dependency is empty",
-                    rule.package
-                );
-            }
+        let infra = &dependency_rules.rules[0].forbidden_dependencies[0].package;
+        assert_eq!(infra.to_string(), "@infra");
+        for name in ["ca-database", "ca-handler", "sqlx-core", "reqwest"] {
+            assert!(infra.matches(&package(name)), "{name} should be in @infra");
         }
+        assert!(!infra.matches(&package("ca-interactor")));
 
-        if rule.forbidden_dependencies.contains(&rule.package) {
-            bail!(
-                "rule for package '{}': package cannot forbid itself",
-                rule.package
-            );
-        }
+        let http = &dependency_rules.rules[1].package;
+        assert!(http.matches(&package("reqwest")));
+        assert!(!http.matches(&package("ca-handler")));
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_validate_undefined_group() {
+        let rules_text = r#"
+            [groups]
+            infra = ["ca-database"]
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-struct RulesSchema {
-    rule: Vec<RuleSchema>,
-}
+            [[rules.rule]]
+            package = "ca-core"
+            forbidden_dependencies = ["@infrastructure"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("rule for package 'ca-core'"));
+        assert!(message.contains("undefined group '@infrastructure'"));
+    }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-struct RuleSchema {
-    package: String,
-    forbidden_dependencies: Vec<String>,
-}
+    #[test]
+    fn test_validate_group_containing_itself() {
+        let rules_text = r#"
+            [groups]
+            infra = ["ca-database", "@storage"]
+            storage = ["sqlx", "@infra"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("group 'infra' contains itself: @infra -> @storage -> @infra"));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use string_auto_indent::auto_indent;
+        let rules_text = r#"
+            [groups]
+            infra = ["ca-database", "@infra"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("group 'infra' contains itself: @infra -> @infra"));
+    }
 
     #[test]
-    fn test_try_from_rules_file_schema_to_dependency_rules() {
-        let rules_file = RulesFileSchema {
-            rules: Some(RulesSchema {
-                rule: vec![RuleSchema {
-                    package: "package1".to_string(),
-                    forbidden_dependencies: vec!["package2".to_string(), "package3".to_string()],
-                }],
-            }),
-        };
-        let expected = DependencyRules {
-            rules: vec![DependencyRule::new(
-                "package1".to_string(),
-                HashSet::from(["package2".to_string(), "package3".to_string()]),
-            )],
-        };
+    fn test_parse_exceptions() {
+        let rules_text = r#"
+            [groups]
+            infra = ["ca-database", "ca-handler"]
 
-        let dependency_rules = DependencyRules::try_from(rules_file).unwrap();
-        assert_eq!(dependency_rules, expected);
+            [[exceptions]]
+            package = "ca-core"
+            dependency = "@infra"
+            reason = "Repository traits are being extracted"
+            owner = "platform-team"
+            expires = 2026-12-31
+
+            [[exceptions]]
+            package = "ca-interactor"
+            dependency = "ca-database"
+            reason = "Written in Cargo.toml metadata"
+            expires = "2027-01-15"
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let exception = &dependency_rules.exceptions[0];
+        assert_eq!(
+            exception.to_string(),
+            "ca-core -> @infra (owner: platform-team)"
+        );
+        assert_eq!(
+            exception.expires,
+            NaiveDate::from_ymd_opt(2026, 12, 31).unwrap()
+        );
+        assert!(exception.matches(&package("ca-core"), &package("ca-handler")));
+        assert!(!exception.is_expired(NaiveDate::from_ymd_opt(2026, 12, 31).unwrap()));
+        assert!(exception.is_expired(NaiveDate::from_ymd_opt(2027, 1, 1).unwrap()));
+
+        assert_eq!(
+            dependency_rules.exceptions[1].expires,
+            NaiveDate::from_ymd_opt(2027, 1, 15).unwrap()
+        );
     }
 
     #[test]
-    fn test_parse_rules_text_inline_table() {
+    fn test_validate_exception_errors() {
         let rules_text = r#"
-            [rules]
-            rule = [
-                {package = "package1", forbidden_dependencies = ["package2", "package3"]},
-            ]
+            [[exceptions]]
+            package = "ca-core"
+            dependency = "ca-database"
+            reason = "Temporary"
+            expires = 2026-12-31T10:00:00
             "#;
-        let expected = RulesFileSchema {
-            rules: Some(RulesSchema {
-                rule: vec![RuleSchema {
-                    package: "package1".to_string(),
-                    forbidden_dependencies: vec!["package2".to_string(), "package3".to_string()],
-                }],
-            }),
-        };
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("exception for 'ca-core -> ca-database'"));
+        assert!(message.contains("invalid expiry date '2026-12-31T10:00:00'"));
 
+        let rules_text = r#"
+            [[exceptions]]
+            package = "ca-core"
+            dependency = "ca-database"
+            reason = ""
+            expires = 2026-12-31
+            "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        assert_eq!(rules, expected);
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("reason must not be empty"));
     }
 
     #[test]
-    fn test_parse_rules_text_table() {
+    fn test_parse_no_cycles() {
         let rules_text = r#"
-            [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = ["package2", "package3"]
-
-            [[rules.rule]]
-            package = "package2"
-            forbidden_dependencies = ["package1"]
+            [no_cycles]
+            kinds = ["normal", "dev"]
+            severity = "warning"
             "#;
-        let expected = RulesFileSchema {
-            rules: Some(RulesSchema {
-                rule: vec![
-                    RuleSchema {
-                        package: "package1".to_string(),
-                        forbidden_dependencies: vec![
-                            "package2".to_string(),
-                            "package3".to_string(),
-                        ],
-                    },
-                    RuleSchema {
-                        package: "package2".to_string(),
-                        forbidden_dependencies: vec!["package1".to_string()],
-                    },
-                ],
-            }),
-        };
-
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        assert_eq!(rules, expected);
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        assert_eq!(
+            dependency_rules.no_cycles,
+            Some(NoCycles {
+                kinds: vec![DependencyKind::Normal, DependencyKind::Development],
+                severity: Severity::Warning,
+                reason: None,
+                origin: None,
+            })
+        );
+
+        let rules: RulesFileSchema = toml::from_str("[no_cycles]").unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+        assert_eq!(
+            dependency_rules.no_cycles.unwrap().kinds,
+            DependencyKindSchema::all()
+        );
+
+        let rules: RulesFileSchema = toml::from_str("[no_cycles]\nkinds = []").unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("no_cycles: kinds must not be empty"));
     }
 
     #[test]
-    fn test_serialize_rules_schema() {
-        let rules = RulesFileSchema {
-            rules: Some(RulesSchema {
-                rule: vec![
-                    RuleSchema {
-                        package: "package1".to_string(),
-                        forbidden_dependencies: vec![
-                            "package2".to_string(),
-                            "package3".to_string(),
-                        ],
-                    },
-                    RuleSchema {
-                        package: "package2".to_string(),
-                        forbidden_dependencies: vec!["package1".to_string()],
-                    },
-                ],
-            }),
-        };
-        let expected = r#"
-            [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = ["package2", "package3"]
+    fn test_parse_budgets() {
+        let rules_text = r#"
+            [[budgets]]
+            package = "ca-core"
+            max_direct = 5
+            max_transitive = 40
 
-            [[rules.rule]]
-            package = "package2"
-            forbidden_dependencies = ["package1"]
+            [[budgets]]
+            package = "*"
+            max_transitive = 250
+            kinds = ["normal"]
+            severity = "warning"
             "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
 
-        let rules_text = toml::to_string(&rules).unwrap();
-        assert_eq!(auto_indent(expected).trim(), rules_text.trim(),);
+        assert_eq!(
+            dependency_rules.budgets[0],
+            Budget {
+                package: PackageMatcher::Exact("ca-core".to_string()),
+                max_direct: Some(5),
+                max_transitive: Some(40),
+                kinds: DependencyKindSchema::all(),
+                severity: Severity::Error,
+                reason: None,
+                origin: None,
+            }
+        );
+        assert_eq!(dependency_rules.budgets[1].max_direct, None);
+        assert_eq!(
+            dependency_rules.budgets[1].kinds,
+            vec![DependencyKind::Normal]
+        );
+        assert_eq!(dependency_rules.budgets[1].severity, Severity::Warning);
     }
 
     #[test]
-    fn test_parse_empty_toml() {
-        let rules_text = "";
+    fn test_validate_budget_without_limits() {
+        let rules_text = r#"
+            [[budgets]]
+            package = "ca-core"
+            "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        let dependency_rules = DependencyRules::try_from(rules).unwrap();
-        assert!(dependency_rules.rules.is_empty());
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("budget for 'ca-core'"));
+        assert!(message.contains("set max_direct, max_transitive or both"));
     }
 
     #[test]
-    fn test_parse_rules_section_without_rule() {
-        let rules_text = "[rules]";
-        let result: Result<RulesFileSchema, _> = toml::from_str(rules_text);
-        assert!(result.is_err());
+    fn test_parse_facades() {
+        let rules_text = r#"
+            [groups]
+            database = ["ca-database", "sqlx"]
+
+            [[facades]]
+            package = "web-*"
+            target = "@database"
+            only_via = ["ca-repositry"]
+            kinds = ["normal"]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let facade = &dependency_rules.facades[0];
+        assert_eq!(facade.package, PackageMatcher::new("web-*").unwrap());
+        assert!(matches!(&facade.target, PackageMatcher::Group { name, .. } if name == "database"));
+        assert_eq!(
+            facade.only_via,
+            vec![PackageMatcher::Exact("ca-repositry".to_string())]
+        );
+        assert_eq!(facade.kinds, vec![DependencyKind::Normal]);
+        assert_eq!(facade.severity, Severity::Error);
     }
 
     #[test]
-    fn test_parse_empty_forbidden_dependencies() {
+    fn test_validate_facade_without_facades() {
         let rules_text = r#"
-            [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = []
+            [[facades]]
+            package = "web-*"
+            target = "ca-database"
+            only_via = []
             "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        let dependency_rules = DependencyRules::try_from(rules).unwrap();
-        assert_eq!(dependency_rules.rules.len(), 1);
-        assert!(dependency_rules.rules[0].forbidden_dependencies.is_empty());
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("facade for 'web-* -> ca-database'"));
+        assert!(message.contains("only_via must not be empty"));
     }
 
     #[test]
-    fn test_parse_invalid_toml_syntax() {
-        let rules_text = "this is not valid toml {{{";
-        let result: Result<RulesFileSchema, _> = toml::from_str(rules_text);
-        assert!(result.is_err());
+    fn test_parse_no_duplicate_versions() {
+        let rules_text = r#"
+            [no_duplicate_versions]
+            skip = ["windows-*"]
+            allow = { syn = 2 }
+            severity = "warning"
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let no_duplicates = DependencyRules::try_from(rules)
+            .unwrap()
+            .no_duplicate_versions
+            .unwrap();
+
+        assert_eq!(no_duplicates.skip.len(), 1);
+        assert!(no_duplicates.is_skipped(&package("windows-sys")));
+        assert_eq!(no_duplicates.allowed_versions("syn"), 2);
+        assert_eq!(no_duplicates.allowed_versions("itoa"), 1);
+        assert_eq!(no_duplicates.severity, Severity::Warning);
+
+        let rules: RulesFileSchema =
+            toml::from_str("[no_duplicate_versions]\nallow = { syn = 1 }").unwrap();
+        let message = format!("{:#}", DependencyRules::try_from(rules).unwrap_err());
+        assert!(message.contains("allow for 'syn' must be at least 2 versions"));
     }
 
     #[test]
-    fn test_validate_empty_package_name() {
+    fn test_parse_licenses() {
         let rules_text = r#"
-            [[rules.rule]]
-            package = ""
-            forbidden_dependencies = ["package2"]
+            [licenses]
+            allow = ["MIT", "Apache-2.0"]
+            deny = ["GPL-3.0-only"]
+            exceptions = [{ package = "ring", allow = ["OpenSSL"] }]
+            severity = "warning"
             "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        let result = DependencyRules::try_from(rules);
-        assert!(result.is_err());
+        let licenses = DependencyRules::try_from(rules).unwrap().licenses.unwrap();
+
+        assert_eq!(licenses.allow.len(), 2);
+        assert_eq!(licenses.deny[0].name, "GPL-3.0");
+        assert_eq!(
+            licenses.exceptions[0].package,
+            PackageMatcher::Exact("ring".to_string())
+        );
+        assert_eq!(licenses.exceptions[0].allow[0].name, "OpenSSL");
+        assert_eq!(licenses.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_validate_license_errors() {
+        let message = |rules_text: &str| {
+            let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+            format!("{:#}", DependencyRules::try_from(rules).unwrap_err())
+        };
+
         assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("empty package name")
+            message("[licenses]\nallow = [\"MIT-ish\"]").contains("unknown SPDX license 'MIT-ish'")
+        );
+        assert!(
+            message("[licenses]\nallow = [\"MIT\"]\ndeny = [\"MIT\"]")
+                .contains("license 'MIT' is both allowed and denied")
+        );
+        assert!(
+            message("[licenses]\nexceptions = [{ package = \"ring\", allow = [\"Nope\"] }]")
+                .contains("exception for 'ring'")
         );
     }
 
     #[test]
-    fn test_validate_empty_forbidden_dependency_name() {
+    fn test_parse_rule_platforms() {
         let rules_text = r#"
             [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = ["package2", ""]
+            package = "app"
+            forbidden_dependencies = ["tokio"]
+            platforms = ['cfg(target_arch = "wasm32")']
             "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        let result = DependencyRules::try_from(rules);
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("forbidden_dependency is empty")
-        );
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(rule.platforms.as_ref().map(Vec::len), Some(1));
+        assert!(rule.applies_to(&edge(DependencyKind::Normal, &[])));
+        assert!(rule.applies_to(&edge(
+            DependencyKind::Normal,
+            &["cfg(target_arch = \"wasm32\")"]
+        )));
+        assert!(rule.applies_to(&edge(
+            DependencyKind::Normal,
+            &["cfg(windows)", "cfg(target_arch = \"wasm32\")"]
+        )));
+        assert!(!rule.applies_to(&edge(DependencyKind::Normal, &["cfg(windows)"])));
     }
 
     #[test]
-    fn test_validate_duplicate_package_rules() {
+    fn test_validate_rule_platforms() {
+        let message = |platforms: &str| {
+            let rules_text = format!(
+                r#"
+                [[rules.rule]]
+                package = "app"
+                forbidden_dependencies = ["tokio"]
+                platforms = {platforms}
+                "#
+            );
+            let rules: RulesFileSchema = toml::from_str(&rules_text).unwrap();
+            format!("{:#}", DependencyRules::try_from(rules).unwrap_err())
+        };
+
+        assert!(message("[]").contains("platforms must not be empty"));
+        let invalid = message(r#"["cfg(any(unix, windows))"]"#);
+        assert!(invalid.contains("rule for package 'app'"));
+        assert!(invalid.contains("may only combine names and key-value pairs with all()"));
+    }
+
+    #[test]
+    fn test_parse_forbidden_features() {
         let rules_text = r#"
             [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = ["package2"]
+            package = "ca-core"
+            forbidden_features = { serde = ["std"], tokio = ["full"] }
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        assert_eq!(
+            dependency_rules.rules[0].forbidden_features,
+            BTreeMap::from([
+                ("serde".to_string(), vec!["std".to_string()]),
+                ("tokio".to_string(), vec!["full".to_string()]),
+            ])
+        );
 
+        let rules_text = r#"
             [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = ["package3"]
+            package = "ca-core"
+            forbidden_features = { serde = [] }
             "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        let result = DependencyRules::try_from(rules);
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("duplicate rule definition for package 'package1'")
-        );
+        let message = DependencyRules::try_from(rules).unwrap_err().to_string();
+        assert!(message.contains("forbidden_features for 'serde' must name a crate"));
     }
 
     #[test]
-    fn test_validate_self_reference() {
+    fn test_parse_no_default_features() {
         let rules_text = r#"
             [[rules.rule]]
-            package = "package1"
-            forbidden_dependencies = ["package1"]
+            package = { label = "embedded" }
+            no_default_features = ["serde", "tokio-*"]
             "#;
         let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
-        let result = DependencyRules::try_from(rules);
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("cannot forbid itself")
-        );
+        let dependency_rules = DependencyRules::try_from(rules).unwrap();
+
+        let rule = &dependency_rules.rules[0];
+        assert_eq!(rule.no_default_features.len(), 2);
+        assert!(rule.no_default_features[1].matches(&package("tokio-util")));
+
+        let rules_text = r#"
+            [[rules.rule]]
+            package = "ca-core"
+            no_default_features = [{ name = "serde", version = "1" }]
+            "#;
+        let rules: RulesFileSchema = toml::from_str(rules_text).unwrap();
+        assert!(DependencyRules::try_from(rules).is_err());
     }
 }
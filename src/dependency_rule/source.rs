@@ -0,0 +1,220 @@
+use anyhow::{Error, anyhow, bail};
+use cargo_metadata::Package;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
+const CRATES_IO_SPARSE_INDEX: &str = "sparse+https://index.crates.io/";
+
+/// Where a package in the dependency graph comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PackageSource<'a> {
+    /// A member of the workspace being checked.
+    Workspace,
+    /// A path dependency outside the workspace.
+    Path,
+    CratesIo,
+    Git(&'a str),
+    /// An alternative registry, identified by its index URL.
+    Registry(&'a str),
+}
+
+impl<'a> PackageSource<'a> {
+    pub(crate) fn of(package: &'a Package, is_workspace_member: bool) -> Self {
+        let Some(source) = &package.source else {
+            return if is_workspace_member {
+                PackageSource::Workspace
+            } else {
+                PackageSource::Path
+            };
+        };
+
+        let repr = source.repr.as_str();
+        if let Some(url) = repr.strip_prefix("git+") {
+            PackageSource::Git(url)
+        } else {
+            let index = repr.strip_prefix("registry+").unwrap_or(repr);
+            if index == CRATES_IO_INDEX || index == CRATES_IO_SPARSE_INDEX {
+                PackageSource::CratesIo
+            } else {
+                PackageSource::Registry(index)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PackageSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageSource::Workspace => f.write_str("workspace"),
+            PackageSource::Path => f.write_str("path"),
+            PackageSource::CratesIo => f.write_str("crates-io"),
+            PackageSource::Git(url) => write!(f, "git+{url}"),
+            PackageSource::Registry(index) => write!(f, "registry {index}"),
+        }
+    }
+}
+
+/// A compiled `sources` / `forbid_sources` selector.
+///
+/// Workspace members are never matched, so allow-lists of sources do not
+/// have to mention the workspace itself.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SourceMatcher {
+    CratesIo,
+    Git,
+    Path,
+    /// Any alternative registry, or only the one with the given index URL.
+    Registry(Option<String>),
+}
+
+impl SourceMatcher {
+    /// Parses `crates-io`, `git`, `path`, `registry` or `registry:<name>`,
+    /// where `<name>` is a key of the `[registries]` table or an index URL.
+    pub(crate) fn new(
+        selector: &str,
+        registries: &BTreeMap<String, String>,
+    ) -> Result<Self, Error> {
+        match selector {
+            "crates-io" => Ok(SourceMatcher::CratesIo),
+            "git" => Ok(SourceMatcher::Git),
+            "path" => Ok(SourceMatcher::Path),
+            "registry" => Ok(SourceMatcher::Registry(None)),
+            _ => {
+                let Some(registry) = selector.strip_prefix("registry:") else {
+                    bail!("unknown source '{selector}'");
+                };
+                let index = match registries.get(registry) {
+                    Some(index) => index.as_str(),
+                    None if registry.contains("://") => registry,
+                    None => {
+                        return Err(anyhow!(
+                            "unknown registry '{registry}', declare its index in [registries]"
+                        ));
+                    }
+                };
+                Ok(SourceMatcher::Registry(Some(normalize_index(index))))
+            }
+        }
+    }
+
+    pub(crate) fn matches(&self, source: &PackageSource<'_>) -> bool {
+        match (self, source) {
+            (SourceMatcher::CratesIo, PackageSource::CratesIo)
+            | (SourceMatcher::Git, PackageSource::Git(_))
+            | (SourceMatcher::Path, PackageSource::Path)
+            | (SourceMatcher::Registry(None), PackageSource::Registry(_)) => true,
+            (SourceMatcher::Registry(Some(expected)), PackageSource::Registry(index)) => {
+                *expected == normalize_index(index)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn normalize_index(index: &str) -> String {
+    index
+        .strip_prefix("registry+")
+        .unwrap_or(index)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_rule::matcher::tests::package;
+    use cargo_metadata::Source;
+
+    fn package_from(repr: &str) -> Package {
+        let mut package = package("dependency");
+        package.source = Some(Source {
+            repr: repr.to_string(),
+        });
+        package
+    }
+
+    fn registries() -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            "internal".to_string(),
+            "sparse+https://cargo.internal.example/index/".to_string(),
+        )])
+    }
+
+    #[test]
+    fn test_package_source_classification() {
+        let crates_io = package_from("registry+https://github.com/rust-lang/crates.io-index");
+        assert_eq!(
+            PackageSource::of(&crates_io, false),
+            PackageSource::CratesIo
+        );
+
+        let sparse = package_from("sparse+https://index.crates.io/");
+        assert_eq!(PackageSource::of(&sparse, false), PackageSource::CratesIo);
+
+        let git = package_from("git+https://github.com/example/repo?branch=main#abc");
+        assert_eq!(
+            PackageSource::of(&git, false),
+            PackageSource::Git("https://github.com/example/repo?branch=main#abc")
+        );
+
+        let registry = package_from("sparse+https://cargo.internal.example/index/");
+        assert_eq!(
+            PackageSource::of(&registry, false),
+            PackageSource::Registry("sparse+https://cargo.internal.example/index/")
+        );
+
+        let local = package("local");
+        assert_eq!(PackageSource::of(&local, true), PackageSource::Workspace);
+        assert_eq!(PackageSource::of(&local, false), PackageSource::Path);
+    }
+
+    #[test]
+    fn test_source_matcher_selectors() {
+        let registries = registries();
+        let git = package_from("git+https://github.com/example/repo#abc");
+        let internal = package_from("sparse+https://cargo.internal.example/index/");
+        let other = package_from("registry+https://other.example/index");
+
+        let matcher = SourceMatcher::new("git", &registries).unwrap();
+        assert!(matcher.matches(&PackageSource::of(&git, false)));
+        assert!(!matcher.matches(&PackageSource::of(&internal, false)));
+
+        let matcher = SourceMatcher::new("registry", &registries).unwrap();
+        assert!(matcher.matches(&PackageSource::of(&internal, false)));
+        assert!(matcher.matches(&PackageSource::of(&other, false)));
+
+        let matcher = SourceMatcher::new("registry:internal", &registries).unwrap();
+        assert!(matcher.matches(&PackageSource::of(&internal, false)));
+        assert!(!matcher.matches(&PackageSource::of(&other, false)));
+
+        let matcher =
+            SourceMatcher::new("registry:https://other.example/index", &registries).unwrap();
+        assert!(matcher.matches(&PackageSource::of(&other, false)));
+
+        let matcher = SourceMatcher::new("path", &registries).unwrap();
+        assert!(matcher.matches(&PackageSource::Path));
+        assert!(!matcher.matches(&PackageSource::Workspace));
+    }
+
+    #[test]
+    fn test_source_matcher_unknown_selectors() {
+        let registries = registries();
+
+        let result = SourceMatcher::new("svn", &registries);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("unknown source 'svn'")
+        );
+
+        let result = SourceMatcher::new("registry:external", &registries);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("unknown registry 'external'")
+        );
+    }
+}
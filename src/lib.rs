@@ -8,12 +8,14 @@ pub mod metadata;
 #[derive(Debug, Clone)]
 pub enum ReturnStatus {
     NoViolation,
+    /// Violations were found, but all of them are below the `--fail-on` level.
+    BelowFailLevel,
     Violation,
 }
 impl ReturnStatus {
     pub fn to_return_code(&self) -> ExitCode {
         match self {
-            ReturnStatus::NoViolation => ExitCode::SUCCESS,
+            ReturnStatus::NoViolation | ReturnStatus::BelowFailLevel => ExitCode::SUCCESS,
             ReturnStatus::Violation => ExitCode::FAILURE,
         }
     }
@@ -24,6 +26,12 @@ pub struct HandlerConfig {
     pub metadata_configs: metadata::CollectMetadataConfig,
     pub tree_config: dependency_graph::tree::TreePrintConfig,
     pub rules_path: Option<PathBuf>,
+    /// Lowest severity that makes the check fail.
+    pub fail_on: dependency_rule::Severity,
+    /// Violations listed in this baseline do not fail the check.
+    pub baseline_path: Option<PathBuf>,
+    /// Writes the current violations as a new baseline.
+    pub write_baseline_path: Option<PathBuf>,
 }
 
 pub fn handler(config: HandlerConfig) -> anyhow::Result<ReturnStatus> {
@@ -33,6 +41,7 @@ pub fn handler(config: HandlerConfig) -> anyhow::Result<ReturnStatus> {
     tracing::info!("building dependency graph");
     let graph = dependency_graph::build_dependency_graph(&metadata, config.graph_build_configs)?;
 
+    let rules_path_required = config.rules_path.is_some();
     let rules_path = match config.rules_path {
         Some(path) => path,
         None => {
@@ -47,15 +56,30 @@ pub fn handler(config: HandlerConfig) -> anyhow::Result<ReturnStatus> {
         }
     };
     tracing::info!(path = ?rules_path, "loading dependency rules");
-    let rules = dependency_rule::DependencyRules::from_file(&rules_path).with_context(|| {
-        format!(
-            "failed to load dependency rules from '{}'",
-            rules_path.display()
-        )
-    })?;
+    let rules = dependency_rule::DependencyRules::for_workspace(
+        &metadata,
+        &rules_path,
+        rules_path_required,
+    )
+    .context("failed to load dependency rules")?;
 
     tracing::info!("checking violations");
-    let report = dependency_graph::violation::check_violations(&graph, &rules);
+    let mut report = dependency_graph::violation::check_violations(&graph, &rules);
+
+    let baseline = match &config.baseline_path {
+        Some(path) => Some(dependency_graph::baseline::Baseline::read(path)?),
+        None => None,
+    };
+    let fixed = match &baseline {
+        Some(baseline) => baseline.apply(&mut report),
+        None => Vec::new(),
+    };
+    if let Some(path) = &config.write_baseline_path {
+        tracing::info!(path = ?path, "writing baseline");
+        let baseline = dependency_graph::baseline::Baseline::from_report(&report);
+        baseline.write(path)?;
+        baseline.apply(&mut report);
+    }
 
     tracing::info!("printing dependency tree");
     dependency_graph::tree::print(
@@ -65,11 +89,13 @@ pub fn handler(config: HandlerConfig) -> anyhow::Result<ReturnStatus> {
         &report,
         config.tree_config,
     )?;
+    dependency_graph::violation::print_summary(&mut std::io::stdout(), &report)?;
+    dependency_graph::baseline::print_fixed(&mut std::io::stdout(), &fixed)?;
 
-    if report.has_violations() {
-        Ok(ReturnStatus::Violation)
-    } else {
-        Ok(ReturnStatus::NoViolation)
+    match report.max_severity() {
+        None => Ok(ReturnStatus::NoViolation),
+        Some(severity) if severity >= config.fail_on => Ok(ReturnStatus::Violation),
+        Some(_) => Ok(ReturnStatus::BelowFailLevel),
     }
 }
 
@@ -78,6 +104,7 @@ mod tests {
     use super::*;
     use crate::{
         dependency_graph::{DependencyGraphBuildConfigs, tree::TreePrintConfig},
+        dependency_rule::Severity,
         metadata::CollectMetadataConfig,
     };
     use anyhow::Result;
@@ -91,6 +118,9 @@ mod tests {
             },
             tree_config: TreePrintConfig::default(),
             rules_path: None,
+            fail_on: Severity::Error,
+            baseline_path: None,
+            write_baseline_path: None,
         }
     }
 
@@ -117,4 +147,56 @@ mod tests {
         assert_eq!(result.to_return_code(), ExitCode::FAILURE);
         Ok(())
     }
+
+    #[test]
+    fn test_handler_fail_on_threshold() -> Result<()> {
+        let mut config = handler_config("tests/demo_crates/tangled-clean-arch/Cargo.toml");
+        config.rules_path = Some(PathBuf::from("tests/test_files/warning_rules_test.toml"));
+        let result = handler(config)?;
+        assert!(matches!(result, ReturnStatus::BelowFailLevel));
+        assert_eq!(result.to_return_code(), ExitCode::SUCCESS);
+
+        let mut config = handler_config("tests/demo_crates/tangled-clean-arch/Cargo.toml");
+        config.rules_path = Some(PathBuf::from("tests/test_files/warning_rules_test.toml"));
+        config.fail_on = Severity::Warning;
+        let result = handler(config)?;
+        assert!(matches!(result, ReturnStatus::Violation));
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_rules_from_workspace_metadata() -> Result<()> {
+        let config = handler_config("tests/demo_crates/metadata-rules/Cargo.toml");
+        let result = handler(config)?;
+        assert_eq!(result.to_return_code(), ExitCode::FAILURE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_with_baseline() -> Result<()> {
+        let dir = std::env::temp_dir().join("check_deprule_test_handler_baseline");
+        std::fs::create_dir_all(&dir)?;
+        let baseline_path = dir.join("baseline.json");
+
+        // writing a baseline accepts every current violation
+        let mut config = handler_config("tests/demo_crates/tangled-clean-arch/Cargo.toml");
+        config.write_baseline_path = Some(baseline_path.clone());
+        let result = handler(config)?;
+        assert_eq!(result.to_return_code(), ExitCode::SUCCESS);
+        assert!(baseline_path.exists());
+
+        let mut config = handler_config("tests/demo_crates/tangled-clean-arch/Cargo.toml");
+        config.baseline_path = Some(baseline_path);
+        let result = handler(config)?;
+        assert!(matches!(result, ReturnStatus::NoViolation));
+
+        // one violation of the demo is missing from this baseline
+        let mut config = handler_config("tests/demo_crates/tangled-clean-arch/Cargo.toml");
+        config.baseline_path = Some(PathBuf::from("tests/test_files/baseline_test.json"));
+        let result = handler(config)?;
+        assert!(matches!(result, ReturnStatus::Violation));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
@@ -7,6 +7,7 @@ use check_deprule::{
         DependencyGraphBuildConfigs,
         tree::{Charset, Prefix, TreePrintConfig},
     },
+    dependency_rule::Severity,
     handler,
     metadata::CollectMetadataConfig,
 };
@@ -39,6 +40,18 @@ struct Cli {
     #[arg(long, value_enum, default_value_t = Prefix::Indent)]
     prefix: Prefix,
 
+    /// Lowest violation severity that makes the check fail
+    #[arg(long, value_enum, default_value_t = Severity::Error)]
+    fail_on: Severity,
+
+    /// Only fail on violations that are not listed in this baseline file
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Write the current violations to a baseline file
+    #[arg(long)]
+    write_baseline: Option<PathBuf>,
+
     /// Log level (overridden by RUST_LOG env var)
     #[arg(long, default_value = "warn")]
     log_level: tracing::Level,
@@ -71,6 +84,9 @@ fn main() -> Result<ExitCode> {
             prefix: cli.prefix,
         },
         rules_path: cli.rules_path,
+        fail_on: cli.fail_on,
+        baseline_path: cli.baseline,
+        write_baseline_path: cli.write_baseline,
     };
 
     let result = handler(config)?;
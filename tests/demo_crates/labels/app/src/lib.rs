@@ -0,0 +1,3 @@
+pub fn add(left: u64, right: u64) -> u64 {
+    left + right
+}